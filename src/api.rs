@@ -1,65 +1,503 @@
-use scheduler::{json_parser::CareerPlan, loaders::json_loader};
-use wasm_bindgen::{prelude::*, JsCast};
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{window, Request, RequestInit, RequestMode, Response};
+use std::{cell::RefCell, collections::HashMap};
 
-use crate::{commissions::Commissions, plan::SubjectPlan, Semester};
+use js_sys::{Date, Function};
+use wasm_bindgen::prelude::*;
 
-async fn fetch(url: &str) -> String {
-    let mut opts = RequestInit::new();
-    opts.method("GET").mode(RequestMode::Cors);
-    let window = window().unwrap();
+use crate::{
+    cache::{CachedResponse, ResponseCache},
+    commissions::Commissions,
+    error::{Result, SchedulerError},
+    index::{
+        Index, IndexPeriod, PeriodDescriptor, PeriodDescriptorArray, PeriodLevel, PeriodSemester,
+        PlanDescriptorArray,
+    },
+    plan::SubjectPlan,
+    transport::{default_transport, CallbackTransport, FixtureTransport, RawRequest, Transport},
+    Level, Semester,
+};
 
-    let request = Request::new_with_str_and_init(url, &opts).unwrap();
-    let resp = JsFuture::from(window.fetch_with_request(&request))
-        .await
-        .unwrap();
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "Record<string, string>")]
+    pub type Fixtures;
+}
 
-    let resp: Response = resp.dyn_into().unwrap();
-    JsFuture::from(resp.text().unwrap())
-        .await
-        .unwrap()
-        .as_string()
-        .unwrap()
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct ApiConfig {
+    commissions_template: String,
+    plan_template: String,
+    index_template: String,
+    level: Level,
+    semester_names: HashMap<Semester, String>,
+    level_names: HashMap<Level, String>,
+    cache_name: Option<String>,
+    conditional_requests: bool,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            commissions_template: "{base}/commissions/{level}-{year}-{semester}.json".to_owned(),
+            plan_template: "{base}/plan/{plan}.json".to_owned(),
+            index_template: "{base}/index.json".to_owned(),
+            level: Level::Graduate,
+            semester_names: HashMap::new(),
+            level_names: HashMap::new(),
+            cache_name: None,
+            conditional_requests: false,
+        }
+    }
+}
+
+impl ApiConfig {
+    fn semester_name(&self, semester: Semester) -> &str {
+        self.semester_names
+            .get(&semester)
+            .map(String::as_str)
+            .unwrap_or_else(|| semester.default_name())
+    }
+
+    fn level_name(&self, level: Level) -> &str {
+        self.level_names
+            .get(&level)
+            .map(String::as_str)
+            .unwrap_or_else(|| level.default_name())
+    }
+
+    fn parse_semester(&self, name: &str) -> Option<Semester> {
+        Semester::ALL
+            .into_iter()
+            .find(|&semester| self.semester_name(semester) == name)
+    }
+
+    fn parse_level(&self, name: &str) -> Option<Level> {
+        Level::ALL
+            .into_iter()
+            .find(|&level| self.level_name(level) == name)
+    }
+
+    fn describe_period(&self, period: IndexPeriod) -> PeriodDescriptor {
+        PeriodDescriptor {
+            year: period.year,
+            semester: match self.parse_semester(&period.semester) {
+                Some(semester) => PeriodSemester::Known(semester),
+                None => PeriodSemester::Unknown(period.semester),
+            },
+            level: match self.parse_level(&period.level) {
+                Some(level) => PeriodLevel::Known(level),
+                None => PeriodLevel::Unknown(period.level),
+            },
+            last_updated: period.last_updated,
+        }
+    }
+
+    fn commissions_url(&self, base: &str, year: u32, semester: Semester, level: Level) -> String {
+        self.commissions_template
+            .replace("{base}", base)
+            .replace("{level}", self.level_name(level))
+            .replace("{year}", &year.to_string())
+            .replace("{semester}", self.semester_name(semester))
+    }
+
+    fn plan_url(&self, base: &str, plan: &str) -> String {
+        self.plan_template
+            .replace("{base}", base)
+            .replace("{plan}", plan)
+    }
+
+    fn index_url(&self, base: &str) -> String {
+        self.index_template.replace("{base}", base)
+    }
+}
+
+#[wasm_bindgen]
+impl ApiConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> ApiConfig {
+        Self::default()
+    }
+
+    /// Template for commission documents. Supports `{base}`, `{level}`, `{year}` and `{semester}`.
+    pub fn set_commissions_template(mut self, template: String) -> ApiConfig {
+        self.commissions_template = template;
+        self
+    }
+
+    /// Template for plan documents. Supports `{base}` and `{plan}`.
+    pub fn set_plan_template(mut self, template: String) -> ApiConfig {
+        self.plan_template = template;
+        self
+    }
+
+    /// Template for the index document listing periods and plans. Supports `{base}`.
+    pub fn set_index_template(mut self, template: String) -> ApiConfig {
+        self.index_template = template;
+        self
+    }
+
+    /// Level used by `get_commissions_from_api`.
+    pub fn set_level(mut self, level: Level) -> ApiConfig {
+        self.level = level;
+        self
+    }
+
+    /// Overrides how `semester` is written in `{semester}`.
+    pub fn set_semester_name(mut self, semester: Semester, name: String) -> ApiConfig {
+        self.semester_names.insert(semester, name);
+        self
+    }
+
+    /// Overrides how `level` is written in `{level}`.
+    pub fn set_level_name(mut self, level: Level, name: String) -> ApiConfig {
+        self.level_names.insert(level, name);
+        self
+    }
+
+    /// Keeps downloaded documents in the named Cache Storage cache, serving them when offline.
+    pub fn set_cache_name(mut self, cache_name: Option<String>) -> ApiConfig {
+        self.cache_name = cache_name;
+        self
+    }
+
+    /// Revalidates cached documents with `If-None-Match`/`If-Modified-Since` instead of downloading
+    /// them again. These headers trigger a CORS preflight, so only enable this when the API allows
+    /// them.
+    pub fn set_conditional_requests(mut self, enabled: bool) -> ApiConfig {
+        self.conditional_requests = enabled;
+        self
+    }
 }
 
 #[wasm_bindgen]
 pub struct Api {
     url_base: String,
+    config: ApiConfig,
+    transport: Box<dyn Transport>,
+    cache: Option<ResponseCache>,
+    /// Why the cached copy of each URL was last served instead of a fresh one.
+    stale: RefCell<HashMap<String, SchedulerError>>,
+}
+
+impl Api {
+    pub fn with_transport(url_base: String, transport: impl Transport + 'static) -> Self {
+        Self::with_boxed_transport(url_base, Box::new(transport))
+    }
+
+    fn with_boxed_transport(url_base: String, transport: Box<dyn Transport>) -> Self {
+        Self {
+            url_base,
+            config: ApiConfig::default(),
+            transport,
+            cache: None,
+            stale: RefCell::default(),
+        }
+    }
+
+    /// Serves the cached copy of `url` after failing to refresh it, remembering why.
+    fn serve_stale<T>(
+        &self,
+        url: &str,
+        cached: CachedResponse,
+        error: SchedulerError,
+        parse: impl Fn(&str) -> Result<T>,
+    ) -> Result<T> {
+        let value = parse(&cached.body).map_err(|_| error.clone())?;
+        self.stale.borrow_mut().insert(url.to_owned(), error);
+        Ok(value)
+    }
+
+    /// Downloads `url` and parses it with `parse`. Only documents that parse are cached, so an
+    /// unexpected page such as a captive portal's doesn't replace a good copy.
+    async fn fetch<T>(&self, url: &str, parse: impl Fn(&str) -> Result<T>) -> Result<T> {
+        let Some(cache) = &self.cache else {
+            let response = self.transport.fetch(&RawRequest::new(url)).await?;
+            return parse(&response.into_body(url)?);
+        };
+
+        // The cache is best effort: failing to read or write it must not stop a download.
+        let cached = cache.get(url).await.ok().flatten();
+        let request = match &cached {
+            Some(cached) if self.config.conditional_requests => cached.revalidate(url),
+            _ => RawRequest::new(url),
+        };
+
+        match (self.transport.fetch(&request).await, cached) {
+            (Ok(response), Some(mut cached)) if response.not_modified() => {
+                let value = parse(&cached.body)?;
+                self.stale.borrow_mut().remove(url);
+                cached.cached_at = Date::now();
+                let _ = cache.put(url, &cached).await;
+                Ok(value)
+            }
+            (Ok(response), cached) if response.ok() => {
+                let fresh = CachedResponse::from(response);
+                match (parse(&fresh.body), cached) {
+                    (Ok(value), _) => {
+                        self.stale.borrow_mut().remove(url);
+                        let _ = cache.put(url, &fresh).await;
+                        Ok(value)
+                    }
+                    (Err(e), Some(cached)) => self.serve_stale(url, cached, e, parse),
+                    (Err(e), None) => Err(e),
+                }
+            }
+            (Ok(response), Some(cached)) if response.status >= 500 => {
+                let error = response.into_body(url).unwrap_err();
+                self.serve_stale(url, cached, error, parse)
+            }
+            (Ok(response), _) => parse(&response.into_body(url)?),
+            (Err(e), Some(cached)) => self.serve_stale(url, cached, e, parse),
+            (Err(e), None) => Err(e),
+        }
+    }
+
+    /// Milliseconds since the cached copy of `url` was last confirmed by the server.
+    async fn cache_age(&self, url: &str) -> Result<Option<f64>> {
+        match &self.cache {
+            Some(cache) => Ok(cache.get(url).await?.map(|c| c.age())),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_index(&self) -> Result<Index> {
+        let url = self.config.index_url(&self.url_base);
+
+        self.fetch(&url, |body| {
+            serde_json::from_str(body).map_err(|e| SchedulerError::json(e).with_url(&url))
+        })
+        .await
+    }
+}
+
+fn to_js<T: serde::Serialize, R: From<JsValue>>(value: &T) -> Result<R> {
+    Ok(serde_wasm_bindgen::to_value(value)?.into())
 }
 
 #[wasm_bindgen]
 impl Api {
     #[wasm_bindgen(constructor)]
-    pub fn new(url_base: String) -> Self {
-        Self { url_base }
+    pub fn new(url_base: String) -> Result<Api> {
+        Ok(Self::with_boxed_transport(url_base, default_transport()?))
+    }
+
+    pub fn set_config(mut self, config: ApiConfig) -> Api {
+        self.cache = config.cache_name.clone().map(ResponseCache::new);
+        self.config = config;
+        self
+    }
+
+    /// Loads every document through `fetch(url)`, which must resolve to a `Response` or a string.
+    pub fn with_fetch(url_base: String, fetch: Function) -> Api {
+        Self::with_transport(url_base, CallbackTransport::new(fetch))
+    }
+
+    /// Serves documents from a map of full URLs to their bodies, without touching the network.
+    pub fn with_fixtures(url_base: String, fixtures: Fixtures) -> Result<Api> {
+        let fixtures: HashMap<String, String> = serde_wasm_bindgen::from_value(fixtures.into())?;
+        Ok(Self::with_transport(
+            url_base,
+            FixtureTransport::new(fixtures),
+        ))
     }
 
     pub async fn get_commissions_from_api(
         &self,
         year: u32,
         semester: Semester,
-    ) -> Option<Commissions> {
-        let url = format!(
-            "{}/commissions/GRADUATE-{}-{}.json",
-            self.url_base,
-            year,
-            match semester {
-                Semester::First => "FirstSemester",
-                Semester::Second => "SecondSemester",
-            }
+    ) -> Result<Commissions> {
+        self.get_level_commissions_from_api(year, semester, self.config.level)
+            .await
+    }
+
+    pub async fn get_level_commissions_from_api(
+        &self,
+        year: u32,
+        semester: Semester,
+        level: Level,
+    ) -> Result<Commissions> {
+        let url = self
+            .config
+            .commissions_url(&self.url_base, year, semester, level);
+
+        self.fetch(&url, |body| {
+            Commissions::from_json(body).map_err(|e| e.with_url(&url))
+        })
+        .await
+    }
+
+    pub async fn get_plan_from_api(&self, plan: &str) -> Result<SubjectPlan> {
+        let url = self.config.plan_url(&self.url_base, plan);
+
+        self.fetch(&url, |body| {
+            SubjectPlan::from_json(body).map_err(|e| e.with_url(&url))
+        })
+        .await
+    }
+
+    /// Lists the periods in the index whose semester and level names this crate recognizes.
+    pub async fn list_periods(&self) -> Result<PeriodDescriptorArray> {
+        let index = self.get_index().await?;
+
+        let periods: Vec<_> = index
+            .periods
+            .into_iter()
+            .map(|period| self.config.describe_period(period))
+            .collect();
+
+        to_js(&periods)
+    }
+
+    pub async fn list_plans(&self) -> Result<PlanDescriptorArray> {
+        let index = self.get_index().await?;
+
+        to_js(&index.plans)
+    }
+
+    pub async fn commissions_cache_age(
+        &self,
+        year: u32,
+        semester: Semester,
+        level: Level,
+    ) -> Result<Option<f64>> {
+        let url = self
+            .config
+            .commissions_url(&self.url_base, year, semester, level);
+        self.cache_age(&url).await
+    }
+
+    pub async fn plan_cache_age(&self, plan: &str) -> Result<Option<f64>> {
+        self.cache_age(&self.config.plan_url(&self.url_base, plan))
+            .await
+    }
+
+    /// Why the commissions were last served from the cache instead of the server, if they were.
+    pub fn commissions_stale_error(
+        &self,
+        year: u32,
+        semester: Semester,
+        level: Level,
+    ) -> Option<SchedulerError> {
+        let url = self
+            .config
+            .commissions_url(&self.url_base, year, semester, level);
+        self.stale.borrow().get(&url).cloned()
+    }
+
+    /// Why the plan was last served from the cache instead of the server, if it was.
+    pub fn plan_stale_error(&self, plan: &str) -> Option<SchedulerError> {
+        let url = self.config.plan_url(&self.url_base, plan);
+        self.stale.borrow().get(&url).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_templates_reproduce_the_baseline_urls() {
+        let config = ApiConfig::default();
+        let base = "http://localhost/api";
+
+        assert_eq!(
+            config.commissions_url(base, 2022, Semester::Second, Level::Graduate),
+            "http://localhost/api/commissions/GRADUATE-2022-SecondSemester.json"
         );
+        assert_eq!(
+            config.plan_url(base, "K08"),
+            "http://localhost/api/plan/K08.json"
+        );
+        assert_eq!(config.index_url(base), "http://localhost/api/index.json");
+    }
 
-        let body = fetch(&url).await;
+    #[test]
+    fn templates_substitute_every_placeholder() {
+        let config = ApiConfig::new()
+            .set_commissions_template("{base}/{year}/{semester}/{level}?again={year}".to_owned())
+            .set_plan_template("{base}/plans?id={plan}".to_owned())
+            .set_index_template("{base}/periods".to_owned())
+            .set_semester_name(Semester::First, "1C".to_owned())
+            .set_level_name(Level::Postgraduate, "posgrado".to_owned());
+        let base = "https://example.com/v2";
 
-        json_loader::load_from_string(&body).ok().map(Commissions::new)
+        assert_eq!(
+            config.commissions_url(base, 2024, Semester::First, Level::Postgraduate),
+            "https://example.com/v2/2024/1C/posgrado?again=2024"
+        );
+        assert_eq!(
+            config.commissions_url(base, 2024, Semester::Summer, Level::Graduate),
+            "https://example.com/v2/2024/Summer/GRADUATE?again=2024"
+        );
+        assert_eq!(
+            config.plan_url(base, "K08"),
+            "https://example.com/v2/plans?id=K08"
+        );
+        assert_eq!(config.index_url(base), "https://example.com/v2/periods");
     }
 
-    pub async fn get_plan_from_api(&self, plan: &str) -> Option<SubjectPlan> {
-        let url = format!("{}/plan/{}.json", self.url_base, plan);
+    #[test]
+    fn renamed_periods_parse_back() {
+        let config = ApiConfig::new()
+            .set_semester_name(Semester::First, "1C".to_owned())
+            .set_level_name(Level::Postgraduate, "posgrado".to_owned());
+
+        assert_eq!(config.parse_semester("1C"), Some(Semester::First));
+        assert_eq!(config.parse_semester("FirstSemester"), None);
+        assert_eq!(config.parse_level("posgrado"), Some(Level::Postgraduate));
+        assert_eq!(config.parse_level("GRADUATE"), Some(Level::Graduate));
+    }
 
-        let body = fetch(&url).await;
+    #[test]
+    fn index_periods_keep_unknown_names() {
+        let index: Index = serde_json::from_str(
+            r#"{
+                "periods": [
+                    {"year": 2024, "semester": "FirstSemester", "level": "GRADUATE",
+                     "lastUpdated": "2024-02-01"},
+                    {"year": 2024, "semester": "Trimester", "level": "POSTGRADUATE"},
+                    {"year": 2023, "semester": "Summer", "level": "DOCTORATE"}
+                ],
+                "plans": [{"id": "K08", "title": "Informatics"}, {"id": "K07"}]
+            }"#,
+        )
+        .unwrap();
+        let config = ApiConfig::default();
 
-        serde_json::from_str::<CareerPlan>(&body).ok().map(SubjectPlan::new)
+        let periods: Vec<_> = index
+            .periods
+            .into_iter()
+            .map(|period| config.describe_period(period))
+            .collect();
+
+        assert_eq!(
+            periods,
+            [
+                PeriodDescriptor {
+                    year: 2024,
+                    semester: PeriodSemester::Known(Semester::First),
+                    level: PeriodLevel::Known(Level::Graduate),
+                    last_updated: Some("2024-02-01".to_owned()),
+                },
+                PeriodDescriptor {
+                    year: 2024,
+                    semester: PeriodSemester::Unknown("Trimester".to_owned()),
+                    level: PeriodLevel::Known(Level::Postgraduate),
+                    last_updated: None,
+                },
+                PeriodDescriptor {
+                    year: 2023,
+                    semester: PeriodSemester::Known(Semester::Summer),
+                    level: PeriodLevel::Unknown("DOCTORATE".to_owned()),
+                    last_updated: None,
+                },
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&periods[1]).unwrap(),
+            serde_json::json!({"year": 2024, "semester": "Trimester", "level": 1})
+        );
+        assert_eq!(index.plans.len(), 2);
+        assert_eq!(index.plans[1].title, None);
     }
 }
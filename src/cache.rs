@@ -0,0 +1,130 @@
+use js_sys::{Date, Reflect};
+use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Cache, CacheStorage, Headers, Response, ResponseInit};
+
+use crate::{
+    error::{js_error_message, Result, SchedulerError},
+    transport::{RawRequest, RawResponse},
+};
+
+const CACHED_AT_HEADER: &str = "X-Scheduler-Cached-At";
+
+pub struct CachedResponse {
+    pub body: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Milliseconds since the epoch at which the body was last confirmed by the server.
+    pub cached_at: f64,
+}
+
+impl CachedResponse {
+    pub fn age(&self) -> f64 {
+        Date::now() - self.cached_at
+    }
+
+    /// A request that only downloads the document again if it changed since it was cached.
+    pub fn revalidate(&self, url: &str) -> RawRequest {
+        RawRequest {
+            url: url.to_owned(),
+            if_none_match: self.etag.clone(),
+            if_modified_since: self.last_modified.clone(),
+        }
+    }
+}
+
+/// Raw API documents stored in the Cache Storage API, keyed by URL.
+pub struct ResponseCache {
+    name: String,
+}
+
+fn cache_error(e: JsValue) -> SchedulerError {
+    SchedulerError::network_error(format!("Cache storage failed: {}", js_error_message(&e)))
+}
+
+impl ResponseCache {
+    pub fn new(name: String) -> Self {
+        Self { name }
+    }
+
+    async fn open(&self) -> Result<Cache> {
+        let caches: CacheStorage = Reflect::get(&js_sys::global(), &"caches".into())
+            .ok()
+            .and_then(|caches| caches.dyn_into().ok())
+            .ok_or_else(|| {
+                SchedulerError::network_error("Cache storage is not available in this environment")
+            })?;
+
+        JsFuture::from(caches.open(&self.name))
+            .await
+            .map_err(cache_error)?
+            .dyn_into()
+            .map_err(cache_error)
+    }
+
+    pub async fn get(&self, url: &str) -> Result<Option<CachedResponse>> {
+        let cache = self.open().await?;
+        let value = JsFuture::from(cache.match_with_str(url))
+            .await
+            .map_err(cache_error)?;
+        if value.is_undefined() {
+            return Ok(None);
+        }
+
+        let resp: Response = value.dyn_into().map_err(cache_error)?;
+        let headers = resp.headers();
+        let body = JsFuture::from(resp.text().map_err(cache_error)?)
+            .await
+            .map_err(cache_error)?
+            .as_string()
+            .unwrap_or_default();
+
+        Ok(Some(CachedResponse {
+            body,
+            etag: headers.get("ETag").ok().flatten(),
+            last_modified: headers.get("Last-Modified").ok().flatten(),
+            cached_at: headers
+                .get(CACHED_AT_HEADER)
+                .ok()
+                .flatten()
+                .and_then(|t| t.parse().ok())
+                .unwrap_or(0.),
+        }))
+    }
+
+    pub async fn put(&self, url: &str, cached: &CachedResponse) -> Result<()> {
+        let headers = Headers::new().map_err(cache_error)?;
+        let fields = [
+            ("ETag", cached.etag.clone()),
+            ("Last-Modified", cached.last_modified.clone()),
+            (CACHED_AT_HEADER, Some(cached.cached_at.to_string())),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                headers.set(name, &value).map_err(cache_error)?;
+            }
+        }
+
+        let mut init = ResponseInit::new();
+        init.status(200).headers(&headers);
+        let resp =
+            Response::new_with_opt_str_and_init(Some(&cached.body), &init).map_err(cache_error)?;
+
+        let cache = self.open().await?;
+        JsFuture::from(cache.put_with_str(url, &resp))
+            .await
+            .map_err(cache_error)?;
+        Ok(())
+    }
+}
+
+impl From<RawResponse> for CachedResponse {
+    fn from(response: RawResponse) -> Self {
+        Self {
+            body: response.body,
+            etag: response.etag,
+            last_modified: response.last_modified,
+            cached_at: Date::now(),
+        }
+    }
+}
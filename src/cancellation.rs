@@ -0,0 +1,149 @@
+use std::{cell::Cell, rc::Rc};
+
+use js_sys::Date;
+use wasm_bindgen::prelude::*;
+use web_sys::AbortSignal;
+
+/// Candidates pulled between two checks of the clock and the cancellation token, a power of two.
+const CHECK_INTERVAL: u64 = 64;
+
+/// Why a `ChoiceGenerator` stopped yielding on its last call.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeneratorStatus {
+    /// The last call yielded what was asked for, more choices may follow.
+    Running,
+    /// Every choice was yielded.
+    Exhausted,
+    /// The time budget ran out, calling again resumes the search.
+    TimedOut,
+    Cancelled,
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    Flag(Rc<Cell<bool>>),
+    Signal(AbortSignal),
+}
+
+/// Stops a generation, through `cancel` or the `AbortSignal` it was created from.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct CancellationToken {
+    source: Source,
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self {
+            source: Source::Flag(Rc::new(Cell::new(false))),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl CancellationToken {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> CancellationToken {
+        Self::default()
+    }
+
+    /// A token cancelled when `signal` is aborted.
+    pub fn from_signal(signal: AbortSignal) -> CancellationToken {
+        Self {
+            source: Source::Signal(signal),
+        }
+    }
+
+    pub fn cancel(&self) {
+        if let Source::Flag(flag) = &self.source {
+            flag.set(true);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        match &self.source {
+            Source::Flag(flag) => flag.get(),
+            Source::Signal(signal) => signal.aborted(),
+        }
+    }
+}
+
+/// Shared between a `ChoiceGenerator` and the guard wrapped around its candidate stream.
+#[derive(Debug, Default)]
+pub struct Watchdog {
+    budget_ms: Option<f64>,
+    token: Option<CancellationToken>,
+    deadline: Cell<Option<f64>>,
+    stopped: Cell<Option<GeneratorStatus>>,
+    examined: Cell<u64>,
+}
+
+impl Watchdog {
+    pub fn new(budget_ms: Option<u32>, token: Option<CancellationToken>) -> Self {
+        Self {
+            budget_ms: budget_ms.map(f64::from),
+            token,
+            ..Default::default()
+        }
+    }
+
+    /// Starts a new time budget, resuming a search that timed out.
+    pub fn arm(&self) {
+        self.deadline
+            .set(self.budget_ms.map(|budget| Date::now() + budget));
+        if self.stopped.get() == Some(GeneratorStatus::TimedOut) {
+            self.stopped.set(None);
+        }
+        self.check();
+    }
+
+    /// Why the guarded stream stopped, if it was cut short.
+    pub fn stopped(&self) -> Option<GeneratorStatus> {
+        self.stopped.get()
+    }
+
+    /// Candidates pulled from the guarded stream so far.
+    pub fn examined(&self) -> u64 {
+        self.examined.get()
+    }
+
+    fn check(&self) {
+        if self.stopped.get().is_some() {
+            return;
+        }
+        if matches!(&self.token, Some(token) if token.is_cancelled()) {
+            self.stopped.set(Some(GeneratorStatus::Cancelled));
+        } else if matches!(self.deadline.get(), Some(deadline) if Date::now() >= deadline) {
+            self.stopped.set(Some(GeneratorStatus::TimedOut));
+        }
+    }
+}
+
+/// Ends the wrapped stream once the watchdog's budget runs out or its token is cancelled.
+pub struct Guarded<I> {
+    inner: I,
+    watchdog: Rc<Watchdog>,
+}
+
+impl<I> Guarded<I> {
+    pub fn new(inner: I, watchdog: Rc<Watchdog>) -> Self {
+        Self { inner, watchdog }
+    }
+}
+
+impl<I: Iterator> Iterator for Guarded<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let watchdog = &self.watchdog;
+        if watchdog.examined.get() & (CHECK_INTERVAL - 1) == 0 {
+            watchdog.check();
+        }
+        if watchdog.stopped.get().is_some() {
+            return None;
+        }
+        watchdog.examined.set(watchdog.examined.get() + 1);
+        self.inner.next()
+    }
+}
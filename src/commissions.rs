@@ -1,23 +1,44 @@
 use std::{cell::RefCell, sync::Arc};
 
-use crate::{generator::GeneratorBuilder, SubjectInfo};
-use anyhow::{anyhow, Result};
-use scheduler::models::{Code, Subject};
+use crate::{
+    details::{parse_details, DetailsTable},
+    error::{Result, SchedulerError},
+    generator::GeneratorBuilder,
+    parse_code, SubjectInfo,
+};
+use scheduler::{
+    loaders::json_loader,
+    models::{Code, Subject, SubjectCommision},
+};
+use serde::de::IgnoredAny;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
 #[derive(Debug, Clone)]
 pub struct Commissions {
     subjects: Arc<Vec<Arc<RefCell<Subject>>>>,
+    /// The document the subjects were loaded from, so workers can load them again.
+    source: Option<Arc<str>>,
+    details: Arc<DetailsTable>,
 }
 
 impl Commissions {
     pub fn new(subjects: Vec<Arc<RefCell<Subject>>>) -> Self {
         Self {
             subjects: Arc::new(subjects),
+            source: None,
+            details: Default::default(),
         }
     }
 
+    pub fn details(&self) -> &Arc<DetailsTable> {
+        &self.details
+    }
+
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
     pub fn find_subject_by_code(&self, code: Code) -> Option<Arc<RefCell<Subject>>> {
         self.subjects
             .iter()
@@ -25,30 +46,66 @@ impl Commissions {
             .cloned()
     }
 
+    pub fn get_subject_by_code(&self, code: Code) -> Result<Arc<RefCell<Subject>>> {
+        self.find_subject_by_code(code)
+            .ok_or_else(|| SchedulerError::unknown_subject(code))
+    }
+
     pub fn find_subjects_by_code(&self, codes: Vec<Code>) -> Result<Vec<Arc<RefCell<Subject>>>> {
         codes
             .into_iter()
-            //.map(|c| c.parse().unwrap())
-            .map(|code| {
-                self.find_subject_by_code(code)
-                    .ok_or_else(|| anyhow!("Subject {} was not found", code))
-            })
+            .map(|code| self.get_subject_by_code(code))
             .collect::<Result<Vec<_>>>()
     }
+
+    pub fn find_commission(&self, code: Code, name: &str) -> Result<SubjectCommision> {
+        let subject = self.get_subject_by_code(code)?;
+        let subject = subject.borrow();
+        subject
+            .commissions
+            .iter()
+            .find(|c| c.names.iter().any(|n| n == name))
+            .cloned()
+            .ok_or_else(|| SchedulerError::unknown_commission(code, name))
+    }
 }
 
 #[wasm_bindgen]
 impl Commissions {
-    pub fn get_subject_info(&self, code: String) -> Option<SubjectInfo> {
-        let code: Code = code.parse().unwrap();
-        self.subjects
+    pub fn from_json(json: &str) -> Result<Commissions> {
+        json_loader::load_from_string(json)
+            .map(|subjects| Commissions {
+                source: Some(json.into()),
+                details: Arc::new(parse_details(json)),
+                ..Commissions::new(subjects)
+            })
+            .map_err(|e| {
+                // Only syntax errors carry a position, so look for one before reporting the
+                // loader's own message.
+                match serde_json::from_str::<IgnoredAny>(json) {
+                    Err(syntax_error) => SchedulerError::json(syntax_error),
+                    Ok(_) => SchedulerError::malformed_input(e.to_string()),
+                }
+            })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Commissions> {
+        let json = std::str::from_utf8(bytes)
+            .map_err(|e| SchedulerError::malformed_input(e.to_string()))?;
+        Self::from_json(json)
+    }
+
+    pub fn get_subject_info(&self, code: String) -> Result<Option<SubjectInfo>> {
+        let code = parse_code(&code)?;
+        Ok(self
+            .subjects
             .iter()
             .find(|s| s.borrow().code == code)
             .map(|s| SubjectInfo {
                 code: s.borrow().code,
                 name: s.borrow().name.clone(),
                 credits: s.borrow().credits,
-            })
+            }))
     }
 
     pub fn create_generator_builder(&self) -> GeneratorBuilder {
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    constraints::BlockedSlot,
+    error::{Result, SchedulerError},
+    schedule::Weekday,
+    scoring::ScoringWeightsInit,
+    utils::{base64url_decode, base64url_encode},
+};
+
+/// Version of the `GeneratorConfig` shape, bumped when a field changes meaning.
+pub const CONFIG_VERSION: u32 = 1;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "GeneratorConfig")]
+    pub type GeneratorConfigObject;
+}
+
+/// A commission as subject code and commission name.
+#[tsify::declare]
+pub type CommissionRef = (String, String);
+
+/// A `GeneratorBuilder` setup without its commissions, to be saved, shared or sent to a worker.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Tsify)]
+pub struct GeneratorConfig {
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mandatory: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub optional: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collision_exceptions: Vec<(CommissionRef, CommissionRef)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_credit_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_credit_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_subject_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_subject_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scoring: Option<ScoringWeightsInit>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_slots: Vec<BlockedSlot>,
+    #[serde(
+        default = "default_blocked_slots_hard",
+        skip_serializing_if = "is_true"
+    )]
+    pub blocked_slots_hard: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_free_days: Vec<Weekday>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_active_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pinned: Vec<CommissionRef>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded: Vec<CommissionRef>,
+}
+
+fn default_blocked_slots_hard() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+impl GeneratorConfig {
+    fn check_version(self) -> Result<Self> {
+        if self.version == 0 || self.version > CONFIG_VERSION {
+            return Err(SchedulerError::malformed_input(format!(
+                "Unsupported generator config version {}",
+                self.version
+            )));
+        }
+        Ok(self)
+    }
+
+    pub fn from_js(config: JsValue) -> Result<Self> {
+        serde_wasm_bindgen::from_value::<Self>(config)?.check_version()
+    }
+
+    pub fn to_js(&self) -> Result<JsValue> {
+        Ok(serde_wasm_bindgen::to_value(self)?)
+    }
+
+    /// The configuration as URL-safe base64 of its JSON, for share links.
+    pub fn to_share_string(&self) -> Result<String> {
+        let json =
+            serde_json::to_vec(self).map_err(|e| SchedulerError::malformed_input(e.to_string()))?;
+        Ok(base64url_encode(&json))
+    }
+
+    pub fn from_share_string(share: &str) -> Result<Self> {
+        let json = base64url_decode(share).ok_or_else(|| {
+            SchedulerError::malformed_input("Share string is not valid URL-safe base64")
+        })?;
+        serde_json::from_slice::<Self>(&json)
+            .map_err(SchedulerError::json)?
+            .check_version()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn base64url_round_trip() {
+        for text in ["", "a", "ab", "abc", "abcd", "{\"version\":1}"] {
+            let encoded = base64url_encode(text.as_bytes());
+            assert!(!encoded.contains(['+', '/', '=']));
+            assert_eq!(base64url_decode(&encoded).unwrap(), text.as_bytes());
+        }
+        assert_eq!(base64url_encode(b"\xfb\xff"), "-_8");
+    }
+
+    #[test]
+    fn unsupported_versions_are_rejected() {
+        for version in [0, CONFIG_VERSION + 1] {
+            let share = base64url_encode(format!("{{\"version\":{version}}}").as_bytes());
+            assert_eq!(
+                GeneratorConfig::from_share_string(&share).map_err(|e| e.kind()),
+                Err(ErrorKind::MalformedInput)
+            );
+        }
+        let share = base64url_encode(format!("{{\"version\":{CONFIG_VERSION}}}").as_bytes());
+        assert!(GeneratorConfig::from_share_string(&share).is_ok());
+    }
+}
@@ -0,0 +1,48 @@
+use scheduler::models::{DaysOfTheWeek, Week};
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+
+use crate::{
+    error::{Result, SchedulerError},
+    schedule::{day_slots, minutes, Weekday},
+    serializer::Time,
+};
+
+/// A range of a day that should be kept free of classes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+pub struct BlockedSlot {
+    pub day: Weekday,
+    pub start: Time,
+    pub end: Time,
+}
+
+impl BlockedSlot {
+    /// Rejects slots with a time outside the day or that don't end after they start.
+    pub fn validate(&self) -> Result<()> {
+        let in_day = |time: Time| time.hour < 24 && time.minutes < 60;
+        if !in_day(self.start) || !in_day(self.end) {
+            return Err(SchedulerError::malformed_input(
+                "Blocked slot times must be within a day",
+            ));
+        }
+        if minutes(self.start.into()) >= minutes(self.end.into()) {
+            return Err(SchedulerError::malformed_input(
+                "Blocked slots must end after they start",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Minutes of `week` that fall inside this slot.
+    pub fn overlap(&self, week: &Week) -> u32 {
+        let (start, end) = (minutes(self.start.into()), minutes(self.end.into()));
+        day_slots(&week.days[DaysOfTheWeek::from(self.day)])
+            .iter()
+            .map(|slot| slot.overlap(start, end))
+            .sum()
+    }
+}
+
+pub fn blocked_minutes(week: &Week, blocked_slots: &[BlockedSlot]) -> u32 {
+    blocked_slots.iter().map(|slot| slot.overlap(week)).sum()
+}
@@ -0,0 +1,18 @@
+//! TypeScript declarations for the names tsify writes when a field's type is not itself
+//! `Tsify`: it names the field after the last segment of the Rust type, so each alias here
+//! declares what one of those names serializes as.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+/// Subject codes serialize as their `HH.LL` string.
+#[tsify::declare]
+pub type Code = String;
+
+/// `EnumMap` serializes as a map from every key to its value.
+#[tsify::declare]
+pub type EnumMap<K, V> = HashMap<K, V>;
+
+/// The name days were declared under before `Weekday` was.
+#[tsify::declare]
+pub type DaysOfTheWeek = crate::schedule::Weekday;
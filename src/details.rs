@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use scheduler::models::Code;
+use serde::Deserialize;
+
+use crate::schedule::Weekday;
+
+/// Where a commission meets at a given time of the week.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassroomSlot {
+    pub day: Weekday,
+    /// Start of the class, in minutes since midnight.
+    pub start: u32,
+    pub classroom: String,
+}
+
+/// Fields of a commission that the scheduler loader doesn't keep.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommissionDetails {
+    pub id: Option<String>,
+    pub teachers: Vec<String>,
+    pub quota: Option<u32>,
+    pub enrolled: Option<u32>,
+    pub classrooms: Vec<ClassroomSlot>,
+}
+
+impl CommissionDetails {
+    pub fn classroom(&self, day: Weekday, start: u32) -> Option<&str> {
+        self.classrooms
+            .iter()
+            .find(|slot| slot.day == day && slot.start == start)
+            .map(|slot| slot.classroom.as_str())
+    }
+}
+
+/// Commission details by subject code and commission name.
+pub type DetailsTable = HashMap<(Code, String), CommissionDetails>;
+
+/// Commissions are read one by one, so a malformed one only loses its own details.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Document {
+    course_commissions: CourseCommissions,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CourseCommissions {
+    course_commission: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawCommission {
+    subject_code: String,
+    commission_name: String,
+    commission_id: Option<String>,
+    quota: Option<Count>,
+    enrolled_students: Option<Count>,
+    #[serde(default)]
+    teachers: Vec<String>,
+    #[serde(default)]
+    course_commission_times: Vec<RawTime>,
+}
+
+/// A count the API sends either as a number or as a string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Count {
+    Number(u32),
+    Text(String),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawTime {
+    day: String,
+    hour_from: String,
+    classroom: Option<String>,
+}
+
+fn non_empty(text: Option<String>) -> Option<String> {
+    text.map(|t| t.trim().to_owned()).filter(|t| !t.is_empty())
+}
+
+fn number(count: Option<Count>) -> Option<u32> {
+    match count? {
+        Count::Number(number) => Some(number),
+        Count::Text(text) => text.trim().parse().ok(),
+    }
+}
+
+/// Minutes since midnight of an `HH:MM` or `HH:MM:SS` time.
+fn time(text: &str) -> Option<u32> {
+    let mut parts = text.trim().split(':');
+    let hour: u32 = parts.next()?.parse().ok()?;
+    let minutes: u32 = parts.next()?.parse().ok()?;
+    Some(hour * 60 + minutes)
+}
+
+impl RawTime {
+    fn classroom_slot(self) -> Option<ClassroomSlot> {
+        Some(ClassroomSlot {
+            day: serde_json::from_value(serde_json::Value::String(self.day.to_lowercase())).ok()?,
+            start: time(&self.hour_from)?,
+            classroom: non_empty(self.classroom)?,
+        })
+    }
+}
+
+impl RawCommission {
+    fn details(self) -> CommissionDetails {
+        CommissionDetails {
+            id: non_empty(self.commission_id),
+            teachers: self
+                .teachers
+                .into_iter()
+                .filter_map(|t| non_empty(Some(t)))
+                .collect(),
+            quota: number(self.quota),
+            enrolled: number(self.enrolled_students),
+            classrooms: self
+                .course_commission_times
+                .into_iter()
+                .filter_map(RawTime::classroom_slot)
+                .collect(),
+        }
+    }
+}
+
+/// Commission details of a commissions document, or none if it has an unexpected shape.
+pub fn parse_details(json: &str) -> DetailsTable {
+    let Ok(document) = serde_json::from_str::<Document>(json) else {
+        return DetailsTable::new();
+    };
+    document
+        .course_commissions
+        .course_commission
+        .into_iter()
+        .filter_map(|commission| {
+            let commission: RawCommission = serde_json::from_value(commission).ok()?;
+            let code = commission.subject_code.trim().parse().ok()?;
+            let name = non_empty(Some(commission.commission_name.clone()))?;
+            Some(((code, name), commission.details()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = r#"
+    {
+        "courseCommissions": {
+            "courseCommission": [
+                {
+                    "subjectCode": "00.00",
+                    "subjectName": "Test subject",
+                    "commissionName": "TEST",
+                    "commissionId": "12345",
+                    "quota": "30",
+                    "enrolledStudents": "1",
+                    "teachers": ["Ada Lovelace", " "],
+                    "courseCommissionTimes": [
+                        {"day": "WEDNESDAY", "hourFrom": "09:00:00", "hourTo": "11:30:00", "classroom": "101"},
+                        {"day": "FRIDAY", "hourFrom": "14:00", "hourTo": "16:00"}
+                    ]
+                }
+            ]
+        }
+    }
+    "#;
+
+    #[test]
+    fn parses_commission_details() {
+        let details = parse_details(DOCUMENT);
+        let commission = &details[&("00.00".parse().unwrap(), "TEST".to_owned())];
+
+        assert_eq!(commission.id.as_deref(), Some("12345"));
+        assert_eq!(commission.quota, Some(30));
+        assert_eq!(commission.enrolled, Some(1));
+        assert_eq!(commission.teachers, vec!["Ada Lovelace".to_owned()]);
+        assert_eq!(
+            commission.classrooms,
+            vec![ClassroomSlot {
+                day: Weekday::Wednesday,
+                start: 9 * 60,
+                classroom: "101".to_owned(),
+            }]
+        );
+        assert_eq!(
+            commission.classroom(Weekday::Wednesday, 9 * 60),
+            Some("101")
+        );
+        assert_eq!(commission.classroom(Weekday::Friday, 14 * 60), None);
+    }
+
+    #[test]
+    fn malformed_commissions_only_lose_their_own_details() {
+        let details = parse_details(
+            r#"
+            {
+                "courseCommissions": {
+                    "courseCommission": [
+                        {"subjectCode": "00.00", "commissionName": "A", "quota": 30, "enrolledStudents": 12},
+                        {"subjectCode": "00.00", "commissionName": "B", "teachers": "Ada Lovelace"},
+                        {"subjectCode": "00.00", "commissionName": "C", "quota": "25"}
+                    ]
+                }
+            }
+            "#,
+        );
+
+        let commission = |name: &str| details.get(&("00.00".parse().unwrap(), name.to_owned()));
+        assert_eq!(commission("A").unwrap().quota, Some(30));
+        assert_eq!(commission("A").unwrap().enrolled, Some(12));
+        assert!(commission("B").is_none());
+        assert_eq!(commission("C").unwrap().quota, Some(25));
+    }
+
+    #[test]
+    fn unexpected_documents_have_no_details() {
+        assert!(parse_details("[]").is_empty());
+        assert!(parse_details(r#"{"courseCommissions": {}}"#).is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+use std::cmp::Ordering;
+
+use itertools::Itertools;
+use scheduler::models::{Code, SubjectCommision};
+use serde::Serialize;
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "Diagnosis")]
+    pub type Diagnosis;
+}
+
+/// A constraint of the builder that can discard commissions or choices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Tsify)]
+#[serde(rename_all = "snake_case")]
+pub enum Constraint {
+    Pinned,
+    Excluded,
+    BlockedSlots,
+    RequiredFreeDays,
+    MaxActiveDays,
+    SubjectCount,
+    CreditCount,
+}
+
+impl Constraint {
+    fn relaxation(&self) -> &'static str {
+        match self {
+            Constraint::Pinned => "Pin another commission",
+            Constraint::Excluded => "Stop excluding commissions",
+            Constraint::BlockedSlots => "Make the blocked slots soft",
+            Constraint::RequiredFreeDays => "Remove a required free day",
+            Constraint::MaxActiveDays => "Raise the maximum number of active days",
+            Constraint::SubjectCount => "Widen the subject count range",
+            Constraint::CreditCount => "Widen the credit range",
+        }
+    }
+}
+
+/// Two mandatory subjects whose remaining commissions all clash with each other.
+#[derive(Debug, Clone, Serialize, Tsify)]
+pub struct Clash {
+    pub subjects: (Code, Code),
+    pub commissions: (Vec<String>, Vec<String>),
+}
+
+/// A subject left without commissions, with every constraint that discarded one of them.
+#[derive(Debug, Clone, Serialize, Tsify)]
+pub struct PrunedSubject {
+    pub subject: Code,
+    pub mandatory: bool,
+    pub constraints: Vec<Constraint>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Tsify)]
+#[serde(rename = "Diagnosis")]
+pub struct DiagnosisInfo {
+    /// Whether at least one choice survived among the examined candidates.
+    pub feasible: bool,
+    /// Candidates produced by the enumeration before the choice level constraints.
+    pub examined: u32,
+    /// Whether the enumeration was cut short by the candidate limit, the time budget or the
+    /// cancellation token, so `eliminated_by` only covers a prefix.
+    pub capped: bool,
+    pub clashes: Vec<Clash>,
+    /// A minimal set of mandatory subjects that can't all be taken together, when no clash
+    /// explains it.
+    pub conflicting_subjects: Vec<Code>,
+    pub pruned_subjects: Vec<PrunedSubject>,
+    /// Choice level constraints that rejected candidates, in the order they are applied. Each
+    /// one only sees the candidates the previous ones let through.
+    pub eliminated_by: Vec<Constraint>,
+    /// The constraint that rejected the last surviving candidates, when none survived.
+    pub last_eliminated_by: Option<Constraint>,
+    pub suggestions: Vec<String>,
+}
+
+pub fn commission_name(commission: &SubjectCommision) -> String {
+    commission.names.first().cloned().unwrap_or_default()
+}
+
+impl DiagnosisInfo {
+    /// Fills `suggestions` from the problems found so far.
+    pub fn suggest(mut self) -> Self {
+        let mut suggestions = vec![];
+        let clash_count = self
+            .clashes
+            .iter()
+            .flat_map(|clash| [clash.subjects.0, clash.subjects.1])
+            .counts();
+        for clash in &self.clashes {
+            let (a, b) = clash.subjects;
+            suggestions.push(match clash_count[&a].cmp(&clash_count[&b]) {
+                Ordering::Greater => format!("Drop subject {a}"),
+                Ordering::Less => format!("Drop subject {b}"),
+                Ordering::Equal => format!("Drop subject {a} or {b}"),
+            });
+            if let (Some(com_a), Some(com_b)) =
+                (clash.commissions.0.first(), clash.commissions.1.first())
+            {
+                suggestions.push(format!(
+                    "Add a collision exception between {a} {com_a} and {b} {com_b}"
+                ));
+            }
+        }
+        if !self.conflicting_subjects.is_empty() {
+            suggestions.push(format!(
+                "Drop one of subjects {}",
+                self.conflicting_subjects.iter().join(", ")
+            ));
+        }
+        for pruned in &self.pruned_subjects {
+            for constraint in &pruned.constraints {
+                suggestions.push(format!(
+                    "{} for subject {}",
+                    constraint.relaxation(),
+                    pruned.subject
+                ));
+            }
+            if pruned.mandatory {
+                suggestions.push(format!("Drop subject {}", pruned.subject));
+            }
+        }
+        if !self.feasible {
+            for constraint in &self.eliminated_by {
+                suggestions.push(constraint.relaxation().to_owned());
+            }
+        }
+        self.suggestions = suggestions.into_iter().unique().collect();
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clash(a: &str, b: &str) -> Clash {
+        Clash {
+            subjects: (a.parse().unwrap(), b.parse().unwrap()),
+            commissions: (vec![], vec![]),
+        }
+    }
+
+    #[test]
+    fn suggests_dropping_the_subject_in_most_clashes() {
+        let diagnosis = DiagnosisInfo {
+            clashes: vec![clash("30.01", "30.02"), clash("30.01", "30.03")],
+            ..Default::default()
+        }
+        .suggest();
+
+        assert_eq!(diagnosis.suggestions, ["Drop subject 30.01"]);
+    }
+
+    #[test]
+    fn suggests_dropping_either_subject_of_an_even_clash() {
+        let diagnosis = DiagnosisInfo {
+            clashes: vec![clash("30.01", "30.02")],
+            ..Default::default()
+        }
+        .suggest();
+
+        assert_eq!(diagnosis.suggestions, ["Drop subject 30.01 or 30.02"]);
+    }
+}
@@ -0,0 +1,176 @@
+use std::fmt;
+
+use wasm_bindgen::{prelude::*, JsCast};
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    InvalidCode,
+    UnknownSubject,
+    UnknownCommission,
+    MalformedInput,
+    NetworkError,
+    WorkerError,
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct SchedulerError {
+    kind: ErrorKind,
+    message: String,
+    url: Option<String>,
+    status: Option<u16>,
+    status_text: Option<String>,
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+pub type Result<T> = std::result::Result<T, SchedulerError>;
+
+impl SchedulerError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            url: None,
+            status: None,
+            status_text: None,
+            line: None,
+            column: None,
+        }
+    }
+
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = Some(url.to_owned());
+        self
+    }
+
+    pub fn with_status(mut self, status: u16, status_text: String) -> Self {
+        self.status = Some(status);
+        self.status_text = Some(status_text);
+        self
+    }
+
+    pub fn with_position(mut self, line: usize, column: usize) -> Self {
+        self.line = Some(line);
+        self.column = Some(column);
+        self
+    }
+
+    pub fn invalid_code(code: &str) -> Self {
+        Self::new(
+            ErrorKind::InvalidCode,
+            format!("\"{code}\" is not a valid subject code"),
+        )
+    }
+
+    pub fn unknown_subject(code: impl fmt::Display) -> Self {
+        Self::new(
+            ErrorKind::UnknownSubject,
+            format!("Subject {code} was not found"),
+        )
+    }
+
+    pub fn unknown_commission(code: impl fmt::Display, name: &str) -> Self {
+        Self::new(
+            ErrorKind::UnknownCommission,
+            format!("Could not find commission {name} from subject {code}"),
+        )
+    }
+
+    pub fn malformed_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::MalformedInput, message)
+    }
+
+    pub fn network_error(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::NetworkError, message)
+    }
+
+    pub fn worker_error(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::WorkerError, message)
+    }
+
+    pub fn http_status(url: &str, status: u16, status_text: String) -> Self {
+        Self::network_error(format!("{url} responded with {status} {status_text}"))
+            .with_url(url)
+            .with_status(status, status_text)
+    }
+
+    /// Wraps a JSON deserialization failure, keeping where in the document it happened.
+    pub fn json(error: serde_json::Error) -> Self {
+        Self::malformed_input(error.to_string()).with_position(error.line(), error.column())
+    }
+}
+
+#[wasm_bindgen]
+impl SchedulerError {
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn url(&self) -> Option<String> {
+        self.url.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn status(&self) -> Option<u16> {
+        self.status
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn status_text(&self) -> Option<String> {
+        self.status_text.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn column(&self) -> Option<usize> {
+        self.column
+    }
+
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for SchedulerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.kind, self.message)?;
+        if let Some(url) = &self.url {
+            write!(f, " ({url})")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SchedulerError {}
+
+impl From<serde_wasm_bindgen::Error> for SchedulerError {
+    fn from(error: serde_wasm_bindgen::Error) -> Self {
+        Self::malformed_input(error.to_string())
+    }
+}
+
+/// Extracts a readable message from a thrown JS value.
+pub(crate) fn js_error_message(value: &JsValue) -> String {
+    value
+        .as_string()
+        .or_else(|| {
+            value
+                .dyn_ref::<js_sys::Error>()
+                .map(|e| String::from(e.message()))
+        })
+        .unwrap_or_else(|| format!("{value:?}"))
+}
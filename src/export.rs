@@ -0,0 +1,292 @@
+use std::{collections::HashMap, fmt::Write};
+
+use enum_map::Enum;
+use itertools::Itertools;
+use serde::Deserialize;
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    error::{Result, SchedulerError},
+    generator::Choice,
+    schedule::{self, Weekday},
+    serializer::{OptionInfo, Task, Time},
+};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "SvgOptions")]
+    pub type SvgOptions;
+}
+
+const DAY_NAMES: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// Fill colors given to subjects without one in `SvgOptions::colors`, in code order.
+const PALETTE: [&str; 10] = [
+    "#8dd3c7", "#ffffb3", "#bebada", "#fb8072", "#80b1d3", "#fdb462", "#b3de69", "#fccde5",
+    "#d9d9d9", "#bc80bd",
+];
+
+const AXIS_WIDTH: u32 = 50;
+const HEADER_HEIGHT: u32 = 30;
+const DAY_WIDTH: u32 = 140;
+const HOUR_HEIGHT: u32 = 48;
+
+fn minutes(time: Time) -> u32 {
+    schedule::minutes(time.into())
+}
+
+fn format_time(time: Time) -> String {
+    format!("{:02}:{:02}", time.hour, time.minutes)
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders a choice as CSV with a header and one row per task, ordered by day and start time.
+#[wasm_bindgen]
+pub fn choice_to_csv(choice: Choice) -> Result<String> {
+    let info = OptionInfo::from_js(choice.into())?;
+    let mut csv = String::from("day,subject,name,commissions,start,end,buildings,classroom\r\n");
+    for (day, tasks) in info.week.iter() {
+        for task in tasks.iter().sorted_by_key(|t| minutes(t.span.start)) {
+            let subject = info.subjects.get(&task.subject);
+            let row = [
+                DAY_NAMES[day.into_usize()].to_owned(),
+                task.subject.to_string(),
+                subject.map(|s| s.name.clone()).unwrap_or_default(),
+                subject.map(|s| s.commissions.join(" ")).unwrap_or_default(),
+                format_time(task.span.start),
+                format_time(task.span.end),
+                task.buildings.join(" "),
+                task.classroom.clone().unwrap_or_default(),
+            ];
+            csv.push_str(&row.iter().map(|f| csv_field(f)).join(","));
+            csv.push_str("\r\n");
+        }
+    }
+    Ok(csv)
+}
+
+#[derive(Default, Deserialize, Tsify)]
+#[serde(rename = "SvgOptions", default)]
+pub struct SvgConfig {
+    start_hour: Option<u32>,
+    end_hour: Option<u32>,
+    /// Fill colors by subject code.
+    colors: Record<String, String>,
+}
+
+/// A map read from a plain object, which tsify declares as a TypeScript `Record`.
+type Record<K, V> = HashMap<K, V>;
+
+/// Each task's lane and the lane count of its group of overlapping tasks.
+fn lanes(tasks: &[&Task]) -> Vec<(usize, usize)> {
+    let mut placed = vec![(0, 1); tasks.len()];
+    let mut group_start = 0;
+    let mut group_end = 0;
+    let mut lane_ends: Vec<u32> = vec![];
+    for (i, task) in tasks.iter().enumerate() {
+        let (start, end) = (minutes(task.span.start), minutes(task.span.end));
+        if start >= group_end {
+            let group_lanes = lane_ends.len().max(1);
+            placed[group_start..i]
+                .iter_mut()
+                .for_each(|p| p.1 = group_lanes);
+            group_start = i;
+            lane_ends.clear();
+        }
+        let lane = match lane_ends.iter().position(|&lane_end| lane_end <= start) {
+            Some(lane) => lane,
+            None => {
+                lane_ends.push(0);
+                lane_ends.len() - 1
+            }
+        };
+        lane_ends[lane] = end;
+        placed[i].0 = lane;
+        group_end = group_end.max(end);
+    }
+    let group_lanes = lane_ends.len().max(1);
+    placed[group_start..]
+        .iter_mut()
+        .for_each(|p| p.1 = group_lanes);
+    placed
+}
+
+/// Renders a choice as a printable weekly timetable.
+#[wasm_bindgen]
+pub fn choice_to_svg(choice: Choice, options: Option<SvgOptions>) -> Result<String> {
+    let info = OptionInfo::from_js(choice.into())?;
+    let config: SvgConfig = match options {
+        Some(options) => serde_wasm_bindgen::from_value(options.into())?,
+        None => SvgConfig::default(),
+    };
+
+    let all_tasks = info.week.values().flatten();
+    let start_hour = config.start_hour.unwrap_or_else(|| {
+        all_tasks
+            .clone()
+            .map(|t| minutes(t.span.start) / 60)
+            .min()
+            .unwrap_or(8)
+    });
+    let end_hour = config.end_hour.unwrap_or_else(|| {
+        all_tasks
+            .map(|t| t.span.end.hour as u32 + u32::from(t.span.end.minutes > 0))
+            .max()
+            .unwrap_or(18)
+    });
+    if end_hour <= start_hour || end_hour > 24 {
+        return Err(SchedulerError::malformed_input(format!(
+            "Invalid hour range {start_hour}-{end_hour}"
+        )));
+    }
+
+    // Tasks may reference subjects missing from `subjects`, they still get a color of their own.
+    let codes = (info.subjects.keys().copied())
+        .chain(info.week.values().flatten().map(|t| t.subject))
+        .unique()
+        .map(|c| c.to_string())
+        .sorted();
+    let colors: HashMap<_, _> = codes
+        .enumerate()
+        .map(|(i, code)| {
+            let color = config
+                .colors
+                .get(&code)
+                .cloned()
+                .unwrap_or_else(|| PALETTE[i % PALETTE.len()].to_owned());
+            (code, color)
+        })
+        .collect();
+
+    let days: Vec<_> = info
+        .week
+        .iter()
+        .filter(|(day, tasks)| {
+            !matches!(day, Weekday::Saturday | Weekday::Sunday) || !tasks.is_empty()
+        })
+        .collect();
+    let width = AXIS_WIDTH + DAY_WIDTH * days.len() as u32;
+    let height = HEADER_HEIGHT + HOUR_HEIGHT * (end_hour - start_hour);
+    let y = |minute: u32| {
+        let minute = minute.clamp(start_hour * 60, end_hour * 60) - start_hour * 60;
+        HEADER_HEIGHT as f64 + minute as f64 * HOUR_HEIGHT as f64 / 60.
+    };
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif" font-size="11">"#
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r##"<rect width="{width}" height="{height}" fill="#ffffff"/>"##
+    )
+    .unwrap();
+    for hour in start_hour..=end_hour {
+        let line_y = y(hour * 60);
+        writeln!(
+            svg,
+            r##"<line x1="{AXIS_WIDTH}" y1="{line_y}" x2="{width}" y2="{line_y}" stroke="#dddddd"/>"##
+        )
+        .unwrap();
+        if hour < end_hour {
+            writeln!(
+                svg,
+                r##"<text x="{}" y="{}" text-anchor="end" fill="#555555">{hour:02}:00</text>"##,
+                AXIS_WIDTH - 6,
+                line_y + 12.
+            )
+            .unwrap();
+        }
+    }
+
+    for (column, (day, tasks)) in days.iter().enumerate() {
+        let x = AXIS_WIDTH + DAY_WIDTH * column as u32;
+        writeln!(
+            svg,
+            r##"<line x1="{x}" y1="0" x2="{x}" y2="{height}" stroke="#dddddd"/>"##
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="middle" font-weight="bold">{}</text>"#,
+            x + DAY_WIDTH / 2,
+            HEADER_HEIGHT - 10,
+            DAY_NAMES[day.into_usize()]
+        )
+        .unwrap();
+
+        let tasks: Vec<_> = tasks
+            .iter()
+            .sorted_by_key(|t| (minutes(t.span.start), minutes(t.span.end)))
+            .collect();
+        for (task, (lane, lane_count)) in tasks.iter().zip(lanes(&tasks)) {
+            let (top, bottom) = (y(minutes(task.span.start)), y(minutes(task.span.end)));
+            if bottom <= top {
+                continue;
+            }
+            let lane_width = (DAY_WIDTH - 4) as f64 / lane_count as f64;
+            let left = x as f64 + 2. + lane_width * lane as f64;
+            let code = task.subject.to_string();
+            let name = info
+                .subjects
+                .get(&task.subject)
+                .map(|s| s.name.as_str())
+                .unwrap_or_default();
+            writeln!(
+                svg,
+                r##"<g><title>{}</title><rect x="{left}" y="{top}" width="{}" height="{}" rx="3" fill="{}" stroke="#333333" stroke-width="0.5"/>"##,
+                xml_escape(&format!(
+                    "{code} {name} {}-{}",
+                    format_time(task.span.start),
+                    format_time(task.span.end)
+                )),
+                lane_width - 2.,
+                bottom - top,
+                xml_escape(colors.get(&code).map_or(PALETTE[0], String::as_str)),
+            )
+            .unwrap();
+            let lines = [code.clone(), name.to_owned(), task.buildings.join(", ")];
+            for (i, line) in lines.iter().filter(|l| !l.is_empty()).enumerate() {
+                let line_y = top + 12. + 12. * i as f64;
+                if line_y > bottom - 2. {
+                    break;
+                }
+                writeln!(
+                    svg,
+                    r#"<text x="{}" y="{line_y}">{}</text>"#,
+                    left + 3.,
+                    xml_escape(line)
+                )
+                .unwrap();
+            }
+            svg.push_str("</g>\n");
+        }
+    }
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
@@ -1,6 +1,7 @@
+use itertools::Itertools;
 use js_sys::Array;
 use scheduler::{
-    models::{Code, Subject, SubjectCommision},
+    models::{Code, DaysOfTheWeek, Subject, SubjectCommision},
     option_generator::{
         filters::{ChoiceIterator, CreditCount, SubjectCount},
         OptionGenerator,
@@ -9,37 +10,74 @@ use scheduler::{
 use wasm_bindgen::prelude::*;
 
 use std::{
-    cell::RefCell,
-    collections::HashSet,
-    iter::FromIterator,
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
     ops::{Bound, RangeBounds},
+    rc::Rc,
     sync::Arc,
 };
 
-use crate::{commissions::Commissions, serializer, CollisionExceptions, StringArray};
+use crate::{
+    cancellation::{CancellationToken, GeneratorStatus, Guarded, Watchdog},
+    commissions::Commissions,
+    config::{CommissionRef, GeneratorConfig, GeneratorConfigObject, CONFIG_VERSION},
+    constraints::{blocked_minutes, BlockedSlot},
+    details::DetailsTable,
+    diagnostics::{commission_name, Clash, Constraint, Diagnosis, DiagnosisInfo, PrunedSubject},
+    error::{Result, SchedulerError},
+    iterator::{install_iterator_protocols, iterator_result},
+    parse_code,
+    schedule::{active_days, merge_week, overlaps, Weekday},
+    scoring::{rank, Scorer, ScoringWeights},
+    serializer, CollisionExceptions, StringArray,
+};
 
-fn parse_codes(codes: impl IntoIterator<Item = String>) -> impl Iterator<Item = Code> {
-    codes.into_iter().map(|c| c.parse().unwrap())
+fn parse_codes(codes: impl IntoIterator<Item = String>) -> Result<Vec<Code>> {
+    codes.into_iter().map(|c| parse_code(&c)).collect()
 }
 
-impl From<CollisionExceptions> for Vec<((String, String), (String, String))> {
-    fn from(ce: CollisionExceptions) -> Self {
+fn parse_pair(value: JsValue) -> Result<Array> {
+    if !Array::is_array(&value) {
+        return Err(SchedulerError::malformed_input(
+            "Collision exceptions must be arrays of pairs",
+        ));
+    }
+    let pair: Array = value.into();
+    if pair.length() != 2 {
+        return Err(SchedulerError::malformed_input(format!(
+            "Expected a pair but got {} elements",
+            pair.length()
+        )));
+    }
+    Ok(pair)
+}
+
+impl TryFrom<CollisionExceptions> for Vec<((String, String), (String, String))> {
+    type Error = SchedulerError;
+
+    fn try_from(ce: CollisionExceptions) -> Result<Self> {
+        if !Array::is_array(&ce) {
+            return Err(SchedulerError::malformed_input(
+                "Collision exceptions must be an array",
+            ));
+        }
         Array::from(&ce)
             .iter()
             .map(|e| {
-                let exception: Array = e.into();
-                assert_eq!(exception.length(), 2);
-                let mut exception = exception.iter().map(|pair| {
-                    let commission: Array = pair.into();
-                    assert_eq!(commission.length(), 2);
-                    let (sub_code, com_name) = (
-                        commission.get(0).as_string().unwrap(),
-                        commission.get(1).as_string().unwrap(),
-                    );
-                    let sub_code = sub_code.parse().unwrap();
-                    (sub_code, com_name)
-                });
-                (exception.next().unwrap(), exception.next().unwrap())
+                let exception = parse_pair(e)?;
+                let parse_commission = |pair: JsValue| -> Result<(String, String)> {
+                    let commission = parse_pair(pair)?;
+                    match (commission.get(0).as_string(), commission.get(1).as_string()) {
+                        (Some(sub_code), Some(com_name)) => Ok((sub_code, com_name)),
+                        _ => Err(SchedulerError::malformed_input(
+                            "Collision exception commissions must be [subject code, commission name]",
+                        )),
+                    }
+                };
+                Ok((
+                    parse_commission(exception.get(0))?,
+                    parse_commission(exception.get(1))?,
+                ))
             })
             .collect()
     }
@@ -77,68 +115,214 @@ impl<Idx: PartialOrd> RangeBounds<Idx> for OptionallyBoundRange<Idx> {
     }
 }
 
-#[wasm_bindgen(typescript_custom_section)]
-const IOPTION: &'static str = r#"
-export type DaysOfTheWeek = "monday" | "tuesday" | "wednesday" | "thursday" | "friday" | "saturday" | "sunday";
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "Choice")]
+    pub type Choice;
 
-export interface Time {
-    hour: number,
-    minutes: number,
-}
+    #[wasm_bindgen(typescript_type = "IteratorResult<Choice>")]
+    pub type ChoiceIteratorResult;
 
-export interface Choice {
-    subjects: Map<String, {
-            name: string,
-            credits: number,
-            commissions: string[],
-    }>,
-    week: Map<DaysOfTheWeek, {
-            subject: string,
-            buildings: string[],
-            span: {
-                start: Time,
-                end: Time,
-            },
-    }[]>,
+    #[wasm_bindgen(typescript_type = "Choice[]")]
+    pub type ChoiceArray;
+
+    #[wasm_bindgen(typescript_type = "BlockedSlot[]")]
+    pub type BlockedSlots;
+
+    #[wasm_bindgen(typescript_type = "DaysOfTheWeek[]")]
+    pub type DaysArray;
+
+    #[wasm_bindgen(typescript_type = "ChoiceGenerator")]
+    pub type ChoiceGeneratorObject;
 }
-"#;
 
+/// How many choices a generator yields, up to an optional limit.
 #[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(typescript_type = "Choice")]
-    pub type Choice;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChoiceCount {
+    pub count: u32,
+    /// Whether counting stopped with choices possibly left, at the limit or because the time
+    /// budget ran out or the search was cancelled.
+    pub capped: bool,
+}
+
+fn count_choices(choices: impl Iterator, limit: Option<u32>, watchdog: &Watchdog) -> ChoiceCount {
+    let mut count = 0;
+    for _ in choices {
+        if Some(count) == limit {
+            return ChoiceCount {
+                count,
+                capped: true,
+            };
+        }
+        count += 1;
+    }
+    ChoiceCount {
+        count,
+        capped: watchdog.stopped().is_some(),
+    }
 }
 
 #[wasm_bindgen]
 pub struct ChoiceGenerator {
     iter: Box<dyn Iterator<Item = Vec<Option<SubjectCommision>>>>,
+    /// A choice taken from `iter` by `count` to tell whether more remain, not handed out yet.
+    peeked: Option<Vec<Option<SubjectCommision>>>,
+    scorer: Option<Scorer>,
+    watchdog: Rc<Watchdog>,
+    status: GeneratorStatus,
+    /// The status once `iter` runs out: `Exhausted`, or why a ranked search was cut short.
+    end: GeneratorStatus,
+    yielded: u32,
+    details: Arc<DetailsTable>,
+}
+
+impl ChoiceGenerator {
+    fn new(
+        iter: Box<dyn Iterator<Item = Vec<Option<SubjectCommision>>>>,
+        scorer: Option<Scorer>,
+        watchdog: Rc<Watchdog>,
+        details: Arc<DetailsTable>,
+    ) -> Self {
+        ChoiceGenerator {
+            iter,
+            peeked: None,
+            scorer,
+            watchdog,
+            status: GeneratorStatus::Running,
+            end: GeneratorStatus::Exhausted,
+            yielded: 0,
+            details,
+        }
+    }
+
+    /// Hands the generator to JS, with the iterator protocols in place.
+    fn into_js(self) -> ChoiceGeneratorObject {
+        let generator = JsValue::from(self);
+        install_iterator_protocols(&generator);
+        generator.unchecked_into()
+    }
+
+    fn pull(&mut self) -> Option<Vec<Option<SubjectCommision>>> {
+        let choice = self.peeked.take().or_else(|| self.iter.next());
+        self.status = match choice {
+            Some(_) => {
+                self.yielded += 1;
+                GeneratorStatus::Running
+            }
+            None => self.watchdog.stopped().unwrap_or(self.end),
+        };
+        choice
+    }
+
+    fn serialize(&self, choice: Vec<Option<SubjectCommision>>) -> Result<JsValue> {
+        let commissions: Vec<_> = choice.into_iter().flatten().collect();
+        let mut info =
+            serializer::OptionInfo::from(commissions.clone()).with_details(&self.details);
+        if let Some(scorer) = &self.scorer {
+            info = info.with_score(scorer.score(&commissions));
+        }
+        Ok(serde_wasm_bindgen::to_value(&info)?)
+    }
 }
 
+/// Every call that pulls choices gets a fresh time budget; `status` tells why it stopped.
 #[wasm_bindgen]
 impl ChoiceGenerator {
-    pub fn next_choice(&mut self) -> Choice {
-        if let Some(choice) = self.iter.next() {
-            let commissions: Vec<_> = choice.into_iter().flatten().collect();
-            serde_wasm_bindgen::to_value::<serializer::OptionInfo>(&commissions.into())
-                .unwrap()
-                .into()
+    pub fn next_choice(&mut self) -> Result<Choice> {
+        self.watchdog.arm();
+        if let Some(choice) = self.pull() {
+            self.serialize(choice).map(Into::into)
         } else {
-            JsValue::null().into()
+            Ok(JsValue::null().into())
+        }
+    }
+
+    /// Iterator protocol counterpart of `next_choice`, also used by `for...of` and
+    /// `for await...of`.
+    pub fn next(&mut self) -> Result<ChoiceIteratorResult> {
+        self.watchdog.arm();
+        let result = match self.pull() {
+            Some(choice) => iterator_result(self.serialize(choice)?, false),
+            None => iterator_result(JsValue::UNDEFINED, true),
+        };
+        Ok(result.into())
+    }
+
+    /// Up to `n` choices in a single call, fewer only when the generator stops.
+    pub fn next_choices(&mut self, n: u32) -> Result<ChoiceArray> {
+        self.watchdog.arm();
+        let batch: Vec<_> = std::iter::from_fn(|| self.pull())
+            .take(n as usize)
+            .collect();
+        let choices = Array::new();
+        for choice in batch {
+            choices.push(&self.serialize(choice)?);
+        }
+        Ok(JsValue::from(choices).into())
+    }
+
+    /// Discards the next `n` choices without serializing them and returns how many were skipped.
+    pub fn skip(&mut self, n: u32) -> u32 {
+        self.watchdog.arm();
+        std::iter::from_fn(|| self.pull()).take(n as usize).count() as u32
+    }
+
+    /// Counts the remaining choices without serializing them, consuming up to `limit` of them.
+    pub fn count(&mut self, limit: Option<u32>) -> ChoiceCount {
+        self.watchdog.arm();
+        let count = std::iter::from_fn(|| self.pull())
+            .take(limit.map_or(usize::MAX, |limit| limit as usize))
+            .count() as u32;
+        if Some(count) == limit && self.peeked.is_none() {
+            self.peeked = self.iter.next();
+        }
+        ChoiceCount {
+            count,
+            capped: self.peeked.is_some() || self.watchdog.stopped().is_some(),
         }
     }
+
+    #[wasm_bindgen(getter)]
+    pub fn status(&self) -> GeneratorStatus {
+        self.status
+    }
+
+    /// Candidates examined so far, including the ones rejected by the choice level constraints.
+    #[wasm_bindgen(getter)]
+    pub fn examined(&self) -> u32 {
+        self.watchdog.examined().min(u32::MAX as u64) as u32
+    }
+
+    /// Choices pulled so far, including skipped and counted ones.
+    #[wasm_bindgen(getter)]
+    pub fn yielded(&self) -> u32 {
+        self.yielded
+    }
 }
 
+type CollisionExceptionSet = HashSet<((Code, SubjectCommision), (Code, SubjectCommision))>;
+
 #[wasm_bindgen]
 #[derive(Debug)]
 pub struct GeneratorBuilder {
     subjects: Commissions,
     mandatory: Vec<Arc<RefCell<Subject>>>,
     optional: Vec<Arc<RefCell<Subject>>>,
-    collision_exceptions: HashSet<((Code, SubjectCommision), (Code, SubjectCommision))>,
+    collision_exceptions: CollisionExceptionSet,
     min_credit_count: Option<u32>,
     max_credit_count: Option<u32>,
     min_subject_count: Option<u32>,
     max_subject_count: Option<u32>,
+    scoring: Option<ScoringWeights>,
+    blocked_slots: Vec<BlockedSlot>,
+    blocked_slots_hard: bool,
+    required_free_days: Vec<Weekday>,
+    max_active_days: Option<u32>,
+    pinned: HashMap<Code, HashSet<SubjectCommision>>,
+    excluded: HashMap<Code, HashSet<SubjectCommision>>,
+    time_budget_ms: Option<u32>,
+    cancellation_token: Option<CancellationToken>,
 }
 
 impl GeneratorBuilder {
@@ -152,10 +336,500 @@ impl GeneratorBuilder {
             max_credit_count: None,
             min_subject_count: None,
             max_subject_count: None,
+            scoring: None,
+            blocked_slots: vec![],
+            blocked_slots_hard: true,
+            required_free_days: vec![],
+            max_active_days: None,
+            pinned: HashMap::new(),
+            excluded: HashMap::new(),
+            time_budget_ms: None,
+            cancellation_token: None,
+        }
+    }
+
+    /// The first constraint that keeps a commission out of every choice, so it can be dropped
+    /// before the enumeration instead of filtering every choice that includes it.
+    fn rejection(&self, code: Code, commission: &SubjectCommision) -> Option<Constraint> {
+        if let Some(pinned) = self.pinned.get(&code) {
+            if !pinned.contains(commission) {
+                return Some(Constraint::Pinned);
+            }
+        }
+        if let Some(excluded) = self.excluded.get(&code) {
+            if excluded.contains(commission) {
+                return Some(Constraint::Excluded);
+            }
+        }
+        let schedule = &commission.schedule;
+        if self.blocked_slots_hard && blocked_minutes(schedule, &self.blocked_slots) > 0 {
+            return Some(Constraint::BlockedSlots);
+        }
+        if self
+            .required_free_days
+            .iter()
+            .any(|&day| !schedule.days[DaysOfTheWeek::from(day)].tasks.is_empty())
+        {
+            return Some(Constraint::RequiredFreeDays);
+        }
+        match self.max_active_days {
+            Some(max_active_days) if active_days(schedule).count() as u32 > max_active_days => {
+                Some(Constraint::MaxActiveDays)
+            }
+            _ => None,
+        }
+    }
+
+    fn allowed_commissions(
+        &self,
+        subjects: &[Arc<RefCell<Subject>>],
+    ) -> Vec<(Code, Vec<SubjectCommision>)> {
+        subjects
+            .iter()
+            .map(|sub| {
+                let sub = sub.borrow();
+                let commissions = sub
+                    .commissions
+                    .iter()
+                    .filter(|c| self.rejection(sub.code, c).is_none())
+                    .cloned()
+                    .collect();
+                (sub.code, commissions)
+            })
+            .collect()
+    }
+
+    fn has_soft_blocked_slots(&self) -> bool {
+        !self.blocked_slots_hard && !self.blocked_slots.is_empty()
+    }
+
+    fn scorer(&self) -> Scorer {
+        Scorer {
+            weights: self.scoring.unwrap_or_default(),
+            blocked_slots: if self.blocked_slots_hard {
+                vec![]
+            } else {
+                self.blocked_slots.clone()
+            },
         }
     }
+
+    /// Every combination of allowed commissions, before the choice level constraints.
+    fn raw_choices(&self) -> impl Iterator<Item = Vec<Option<SubjectCommision>>> {
+        self.optimize();
+
+        let mut generator = OptionGenerator::default();
+        generator
+            .set_mandatory(self.allowed_commissions(&self.mandatory))
+            .set_optional(self.allowed_commissions(&self.optional))
+            .set_collission_exceptions(self.collision_exceptions.clone());
+        generator.generate()
+    }
+
+    fn subject_count(&self) -> OptionallyBoundRange<u32> {
+        OptionallyBoundRange::new(self.min_subject_count, self.max_subject_count)
+    }
+
+    fn credit_count(&self) -> OptionallyBoundRange<u32> {
+        OptionallyBoundRange::new(self.min_credit_count, self.max_credit_count)
+    }
+
+    fn watchdog(&self) -> Rc<Watchdog> {
+        Rc::new(Watchdog::new(
+            self.time_budget_ms,
+            self.cancellation_token.clone(),
+        ))
+    }
+
+    fn choices(
+        &self,
+        watchdog: &Rc<Watchdog>,
+    ) -> Box<dyn Iterator<Item = Vec<Option<SubjectCommision>>>> {
+        let max_active_days = self.max_active_days;
+        Box::new(
+            Guarded::new(self.raw_choices(), watchdog.clone())
+                .filter_choices(SubjectCount::new(self.subject_count()))
+                .filter_choices(CreditCount::new(self.credit_count()))
+                .filter(move |choice| within_active_days(choice, max_active_days)),
+        )
+    }
+
+    pub fn commissions(&self) -> &Commissions {
+        &self.subjects
+    }
+
+    fn resolve_commission(
+        &self,
+        sub_code: &str,
+        com_name: &str,
+    ) -> Result<(Code, SubjectCommision)> {
+        let code = parse_code(sub_code)?;
+        Ok((code, self.subjects.find_commission(code, com_name)?))
+    }
+
+    fn resolve_exceptions(
+        &self,
+        exceptions: &[(CommissionRef, CommissionRef)],
+    ) -> Result<CollisionExceptionSet> {
+        exceptions
+            .iter()
+            .map(|((sub_a, com_a), (sub_b, com_b))| {
+                Ok((
+                    self.resolve_commission(sub_a, com_a)?,
+                    self.resolve_commission(sub_b, com_b)?,
+                ))
+            })
+            .collect()
+    }
+
+    fn resolve_commission_sets(
+        &self,
+        commissions: &[CommissionRef],
+    ) -> Result<HashMap<Code, HashSet<SubjectCommision>>> {
+        let mut sets: HashMap<_, HashSet<_>> = HashMap::new();
+        for (sub_code, com_name) in commissions {
+            let (code, commission) = self.resolve_commission(sub_code, com_name)?;
+            sets.entry(code).or_default().insert(commission);
+        }
+        Ok(sets)
+    }
+
+    pub fn with_config(subjects: Commissions, config: &GeneratorConfig) -> Result<Self> {
+        let mut builder = Self::new(subjects);
+        builder.mandatory = builder
+            .subjects
+            .find_subjects_by_code(parse_codes(config.mandatory.iter().cloned())?)?;
+        builder.optional = builder
+            .subjects
+            .find_subjects_by_code(parse_codes(config.optional.iter().cloned())?)?;
+        builder.collision_exceptions = builder.resolve_exceptions(&config.collision_exceptions)?;
+        builder.pinned = builder.resolve_commission_sets(&config.pinned)?;
+        builder.excluded = builder.resolve_commission_sets(&config.excluded)?;
+        builder.min_credit_count = config.min_credit_count;
+        builder.max_credit_count = config.max_credit_count;
+        builder.min_subject_count = config.min_subject_count;
+        builder.max_subject_count = config.max_subject_count;
+        builder.scoring = config.scoring;
+        config
+            .blocked_slots
+            .iter()
+            .try_for_each(BlockedSlot::validate)?;
+        builder.blocked_slots = config.blocked_slots.clone();
+        builder.blocked_slots_hard = config.blocked_slots_hard;
+        builder.required_free_days = config.required_free_days.clone();
+        builder.max_active_days = config.max_active_days;
+        Ok(builder)
+    }
+
+    pub fn config(&self) -> GeneratorConfig {
+        let codes = |subjects: &[Arc<RefCell<Subject>>]| {
+            subjects
+                .iter()
+                .map(|s| s.borrow().code.to_string())
+                .collect()
+        };
+        let commission_refs = |sets: &HashMap<Code, HashSet<SubjectCommision>>| {
+            sets.iter()
+                .flat_map(|(code, commissions)| {
+                    commissions.iter().flat_map(|c| every_name(*code, c))
+                })
+                .sorted()
+                .dedup()
+                .collect()
+        };
+        GeneratorConfig {
+            version: CONFIG_VERSION,
+            mandatory: codes(&self.mandatory),
+            optional: codes(&self.optional),
+            collision_exceptions: self
+                .collision_exceptions
+                .iter()
+                .flat_map(|((code_a, com_a), (code_b, com_b))| {
+                    every_name(*code_a, com_a).cartesian_product(every_name(*code_b, com_b))
+                })
+                .sorted()
+                .dedup()
+                .collect(),
+            min_credit_count: self.min_credit_count,
+            max_credit_count: self.max_credit_count,
+            min_subject_count: self.min_subject_count,
+            max_subject_count: self.max_subject_count,
+            scoring: self.scoring,
+            blocked_slots: self.blocked_slots.clone(),
+            blocked_slots_hard: self.blocked_slots_hard,
+            required_free_days: self.required_free_days.clone(),
+            max_active_days: self.max_active_days,
+            pinned: commission_refs(&self.pinned),
+            excluded: commission_refs(&self.excluded),
+        }
+    }
+
+    /// Splits the search space in up to `parts` disjoint configurations that together yield
+    /// the same choices. The first mandatory subject is split by commission; without mandatory
+    /// subjects, the first optional one is either dropped or made mandatory with a share of its
+    /// commissions.
+    pub fn partitions(&self, parts: u32) -> Vec<GeneratorConfig> {
+        let config = self.config();
+        if parts <= 1 {
+            return vec![config];
+        }
+        let (subject, mandatory) = match (self.mandatory.first(), self.optional.first()) {
+            (Some(subject), _) => (subject, true),
+            (None, Some(subject)) => (subject, false),
+            (None, None) => return vec![config],
+        };
+        let (code, commissions) = self
+            .allowed_commissions(std::slice::from_ref(subject))
+            .remove(0);
+        if commissions.is_empty() {
+            return vec![config];
+        }
+        let subject_code = code;
+        let code = code.to_string();
+        let groups = if mandatory {
+            parts
+        } else {
+            parts.saturating_sub(1)
+        }
+        .clamp(1, commissions.len().max(1) as u32) as usize;
+
+        let pinned = (0..groups).map(|group| {
+            let mut part = config.clone();
+            part.pinned.retain(|(sub_code, _)| *sub_code != code);
+            part.pinned.extend(
+                commissions
+                    .iter()
+                    .skip(group)
+                    .step_by(groups)
+                    .flat_map(|c| every_name(subject_code, c)),
+            );
+            if !mandatory {
+                part.optional.retain(|sub_code| *sub_code != code);
+                part.mandatory.insert(0, code.clone());
+            }
+            part
+        });
+        if mandatory {
+            pinned.collect()
+        } else {
+            let mut dropped = config.clone();
+            dropped.optional.retain(|sub_code| *sub_code != code);
+            std::iter::once(dropped).chain(pinned).collect()
+        }
+    }
+
+    /// Enumerates up to `DIAGNOSE_LIMIT` candidates once, within the time budget, applying the
+    /// choice level constraints in order and counting the candidates left after each of them.
+    pub fn diagnosis(&self) -> DiagnosisInfo {
+        let watchdog = self.watchdog();
+        watchdog.arm();
+        let mut candidates = Guarded::new(self.raw_choices(), watchdog.clone());
+        let max_active_days = self.max_active_days;
+
+        let survivors: [Cell<u32>; 3] = Default::default();
+        let tally = |stage: usize| {
+            let survivors = &survivors[stage];
+            move |_: &Vec<Option<SubjectCommision>>| survivors.set(survivors.get() + 1)
+        };
+        let accepted = candidates
+            .by_ref()
+            .take(DIAGNOSE_LIMIT)
+            .inspect(tally(0))
+            .filter_choices(SubjectCount::new(self.subject_count()))
+            .inspect(tally(1))
+            .filter_choices(CreditCount::new(self.credit_count()))
+            .inspect(tally(2))
+            .filter(|choice| within_active_days(choice, max_active_days))
+            .count() as u32;
+        let capped = watchdog.stopped().is_some() || candidates.next().is_some();
+
+        let [examined, subject_count, credit_count] = survivors.map(Cell::into_inner);
+        let survivors = [examined, subject_count, credit_count, accepted];
+        let stages = [
+            Constraint::SubjectCount,
+            Constraint::CreditCount,
+            Constraint::MaxActiveDays,
+        ];
+        let eliminated = |i: usize| survivors[i + 1] < survivors[i];
+        let feasible = survivors[3] > 0;
+        let clashes = self.clashes();
+        let conflicting_subjects = if feasible || !clashes.is_empty() {
+            vec![]
+        } else {
+            self.conflicting_subjects(&watchdog)
+        };
+        DiagnosisInfo {
+            feasible,
+            examined: survivors[0],
+            capped: capped || watchdog.stopped().is_some(),
+            clashes,
+            conflicting_subjects,
+            pruned_subjects: self.pruned_subjects(),
+            eliminated_by: (0..stages.len())
+                .filter(|&i| eliminated(i))
+                .map(|i| stages[i])
+                .collect(),
+            last_eliminated_by: (0..stages.len())
+                .find(|&i| eliminated(i) && survivors[i + 1] == 0)
+                .map(|i| stages[i]),
+            ..Default::default()
+        }
+        .suggest()
+    }
+
+    /// The generator behind `build`, for callers on the Rust side.
+    pub fn generator(self) -> ChoiceGenerator {
+        let scorer =
+            (self.scoring.is_some() || self.has_soft_blocked_slots()).then(|| self.scorer());
+        let watchdog = self.watchdog();
+        let details = self.subjects.details().clone();
+        ChoiceGenerator::new(self.choices(&watchdog), scorer, watchdog, details)
+    }
+
+    /// The generator behind `build_ranked`.
+    pub fn ranked_generator(self, top_k: u32) -> ChoiceGenerator {
+        let scorer = self.scorer();
+        let watchdog = self.watchdog();
+        watchdog.arm();
+        let ranked = rank(self.choices(&watchdog), &scorer, top_k as usize);
+        let mut generator = ChoiceGenerator::new(
+            Box::new(ranked.into_iter()),
+            Some(scorer),
+            Default::default(),
+            self.subjects.details().clone(),
+        );
+        if let Some(status) = watchdog.stopped() {
+            generator.status = status;
+            generator.end = status;
+        }
+        generator
+    }
+
+    /// Mandatory subjects whose remaining commissions all clash pairwise.
+    fn clashes(&self) -> Vec<Clash> {
+        let mandatory = self.allowed_commissions(&self.mandatory);
+        let excepted = |a: (Code, SubjectCommision), b: (Code, SubjectCommision)| {
+            self.collision_exceptions.contains(&(a.clone(), b.clone()))
+                || self.collision_exceptions.contains(&(b, a))
+        };
+        mandatory
+            .iter()
+            .tuple_combinations()
+            .filter(|((_, coms_a), (_, coms_b))| !coms_a.is_empty() && !coms_b.is_empty())
+            .filter(|((code_a, coms_a), (code_b, coms_b))| {
+                coms_a.iter().all(|a| {
+                    coms_b.iter().all(|b| {
+                        overlaps(&a.schedule, &b.schedule)
+                            && !excepted((*code_a, a.clone()), (*code_b, b.clone()))
+                    })
+                })
+            })
+            .map(|((code_a, coms_a), (code_b, coms_b))| Clash {
+                subjects: (*code_a, *code_b),
+                commissions: (
+                    coms_a.iter().map(commission_name).collect(),
+                    coms_b.iter().map(commission_name).collect(),
+                ),
+            })
+            .collect()
+    }
+
+    /// Whether the mandatory `subjects` fit together, ignoring every other subject and constraint.
+    fn fits(&self, subjects: &[(Code, Vec<SubjectCommision>)], watchdog: &Rc<Watchdog>) -> bool {
+        let mut generator = OptionGenerator::default();
+        generator
+            .set_mandatory(subjects.to_vec())
+            .set_collission_exceptions(self.collision_exceptions.clone());
+        Guarded::new(generator.generate(), watchdog.clone())
+            .next()
+            .is_some()
+    }
+
+    /// A minimal set of mandatory subjects that don't fit together, found by dropping every
+    /// subject the conflict doesn't need. Empty when they fit or the watchdog stopped the search.
+    fn conflicting_subjects(&self, watchdog: &Rc<Watchdog>) -> Vec<Code> {
+        let mut subjects = self.allowed_commissions(&self.mandatory);
+        // Subjects without commissions are reported as pruned instead.
+        subjects.retain(|(_, commissions)| !commissions.is_empty());
+        if self.fits(&subjects, watchdog) {
+            return vec![];
+        }
+        let mut i = 0;
+        while i < subjects.len() {
+            let subject = subjects.remove(i);
+            if self.fits(&subjects, watchdog) {
+                subjects.insert(i, subject);
+                i += 1;
+            }
+        }
+        if watchdog.stopped().is_some() {
+            return vec![];
+        }
+        subjects.into_iter().map(|(code, _)| code).collect()
+    }
+
+    /// Subjects left without commissions and the constraints that discarded them.
+    fn pruned_subjects(&self) -> Vec<PrunedSubject> {
+        let subjects = (self.mandatory.iter().map(|s| (s, true)))
+            .chain(self.optional.iter().map(|s| (s, false)));
+        subjects
+            .filter_map(|(sub, mandatory)| {
+                let sub = sub.borrow();
+                let rejections: Vec<_> = sub
+                    .commissions
+                    .iter()
+                    .map(|c| self.rejection(sub.code, c))
+                    .collect();
+                if rejections.iter().any(Option::is_none) {
+                    return None;
+                }
+                Some(PrunedSubject {
+                    subject: sub.code,
+                    mandatory,
+                    constraints: rejections.into_iter().flatten().unique().collect(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Removes `commission` from the set of `code`, dropping the set once it is empty.
+fn remove_commission(
+    sets: &mut HashMap<Code, HashSet<SubjectCommision>>,
+    code: Code,
+    commission: &SubjectCommision,
+) {
+    if let Some(set) = sets.get_mut(&code) {
+        set.remove(commission);
+        if set.is_empty() {
+            sets.remove(&code);
+        }
+    }
+}
+
+/// `commission` under each of its names, so merged commissions resolve when loaded apart.
+fn every_name(
+    code: Code,
+    commission: &SubjectCommision,
+) -> impl Iterator<Item = CommissionRef> + Clone + '_ {
+    commission
+        .names
+        .iter()
+        .map(move |name| (code.to_string(), name.clone()))
+}
+
+fn within_active_days(choice: &[Option<SubjectCommision>], max_active_days: Option<u32>) -> bool {
+    match max_active_days {
+        Some(max_active_days) => {
+            active_days(&merge_week(choice.iter().flatten())).count() as u32 <= max_active_days
+        }
+        None => true,
+    }
 }
 
+/// Candidates examined by `diagnose` before giving up on finding a surviving choice.
+const DIAGNOSE_LIMIT: usize = 10_000;
+
 #[wasm_bindgen]
 impl GeneratorBuilder {
     pub fn set_min_credit_count(mut self, min_credit_count: Option<u32>) -> Self {
@@ -178,50 +852,28 @@ impl GeneratorBuilder {
         self
     }
 
-    pub fn set_mandatory_codes(mut self, mandatory_codes: StringArray) -> Self {
+    pub fn set_mandatory_codes(mut self, mandatory_codes: StringArray) -> Result<GeneratorBuilder> {
         self.mandatory = self
             .subjects
-            .find_subjects_by_code(parse_codes(Vec::<String>::from(mandatory_codes)).collect())
-            .unwrap();
-        self
+            .find_subjects_by_code(parse_codes(Vec::<String>::try_from(mandatory_codes)?)?)?;
+        Ok(self)
     }
 
-    pub fn set_optional_codes(mut self, optional_codes: StringArray) -> Self {
+    pub fn set_optional_codes(mut self, optional_codes: StringArray) -> Result<GeneratorBuilder> {
         self.optional = self
             .subjects
-            .find_subjects_by_code(parse_codes(Vec::<String>::from(optional_codes)).collect())
-            .unwrap();
-        self
+            .find_subjects_by_code(parse_codes(Vec::<String>::try_from(optional_codes)?)?)?;
+        Ok(self)
     }
 
-    pub fn set_collision_exceptions(mut self, collision_exceptions: CollisionExceptions) -> Self {
+    pub fn set_collision_exceptions(
+        mut self,
+        collision_exceptions: CollisionExceptions,
+    ) -> Result<GeneratorBuilder> {
         let collision_exceptions: Vec<((String, String), (String, String))> =
-            collision_exceptions.into();
-        let find_commission = |sub_code: Code, com_name: String| {
-            let sub = self
-                .subjects
-                .find_subject_by_code(sub_code)
-                .unwrap_or_else(|| panic!("Coud not find subject {sub_code}"));
-            let sub = sub.borrow();
-            sub.commissions
-                .iter()
-                .find(|c| c.names.iter().any(|name| name == &com_name))
-                .unwrap_or_else(|| {
-                    panic!("Could not find commission {com_name} from subject {sub_code}.")
-                })
-                .clone()
-        };
-        self.collision_exceptions = HashSet::from_iter(collision_exceptions.into_iter().map(
-            |((sub_a, com_a), (sub_b, com_b))| {
-                let code_a = sub_a.parse().unwrap();
-                let code_b = sub_b.parse().unwrap();
-                (
-                    (code_a, find_commission(code_a, com_a)),
-                    (code_b, find_commission(code_b, com_b)),
-                )
-            },
-        ));
-        self
+            collision_exceptions.try_into()?;
+        self.collision_exceptions = self.resolve_exceptions(&collision_exceptions)?;
+        Ok(self)
     }
 
     pub fn optimize(&self) {
@@ -233,37 +885,126 @@ impl GeneratorBuilder {
             .for_each(|sub| sub.borrow_mut().optimize());
     }
 
-    pub fn build(self) -> ChoiceGenerator {
-        self.optimize();
+    /// Attaches a score breakdown to every choice and sets the weights used by `build_ranked`.
+    pub fn set_scoring(mut self, weights: &ScoringWeights) -> GeneratorBuilder {
+        self.scoring = Some(*weights);
+        self
+    }
 
-        let find_commissions = |subjects: Vec<Arc<RefCell<Subject>>>| {
-            subjects
-                .into_iter()
-                .map(|sub| (sub.borrow().code, sub.borrow().commissions.clone()))
-                .collect::<Vec<_>>()
-        };
-        let mandatory = find_commissions(self.mandatory);
-        let optional = find_commissions(self.optional);
+    /// Keeps choices away from the given slots. Hard slots discard any commission with a class
+    /// inside them, soft slots only add their overlap to the `blocked_minutes` score, which is
+    /// attached to every choice even without `set_scoring`.
+    pub fn set_blocked_slots(
+        mut self,
+        blocked_slots: BlockedSlots,
+        hard: bool,
+    ) -> Result<GeneratorBuilder> {
+        let blocked_slots: Vec<BlockedSlot> = serde_wasm_bindgen::from_value(blocked_slots.into())?;
+        blocked_slots.iter().try_for_each(BlockedSlot::validate)?;
+        self.blocked_slots = blocked_slots;
+        self.blocked_slots_hard = hard;
+        Ok(self)
+    }
 
-        let mut generator = OptionGenerator::default();
-        generator
-            .set_mandatory(mandatory)
-            .set_optional(optional)
-            .set_collission_exceptions(self.collision_exceptions);
+    /// Restricts the subject to its pinned commissions. Pinning several commissions of the same
+    /// subject allows any of them. Pinning an excluded commission stops excluding it.
+    pub fn pin_commission(
+        mut self,
+        subject_code: String,
+        commission_name: String,
+    ) -> Result<GeneratorBuilder> {
+        let (code, commission) = self.resolve_commission(&subject_code, &commission_name)?;
+        remove_commission(&mut self.excluded, code, &commission);
+        self.pinned.entry(code).or_default().insert(commission);
+        Ok(self)
+    }
 
-        ChoiceGenerator {
-            iter: Box::new(
-                generator
-                    .generate()
-                    .filter_choices(SubjectCount::new(OptionallyBoundRange::new(
-                        self.min_subject_count,
-                        self.max_subject_count,
-                    )))
-                    .filter_choices(CreditCount::new(OptionallyBoundRange::new(
-                        self.min_credit_count,
-                        self.max_credit_count,
-                    ))),
-            ),
-        }
+    /// Never considers the given commission of the subject. Excluding a pinned commission
+    /// unpins it.
+    pub fn exclude_commission(
+        mut self,
+        subject_code: String,
+        commission_name: String,
+    ) -> Result<GeneratorBuilder> {
+        let (code, commission) = self.resolve_commission(&subject_code, &commission_name)?;
+        remove_commission(&mut self.pinned, code, &commission);
+        self.excluded.entry(code).or_default().insert(commission);
+        Ok(self)
+    }
+
+    /// Discards every commission with classes on any of `days`.
+    pub fn set_required_free_days(mut self, days: DaysArray) -> Result<GeneratorBuilder> {
+        self.required_free_days = serde_wasm_bindgen::from_value(days.into())?;
+        Ok(self)
+    }
+
+    /// Limits how long each call pulling choices from the generator may search, in milliseconds.
+    pub fn set_time_budget_ms(mut self, time_budget_ms: Option<u32>) -> GeneratorBuilder {
+        self.time_budget_ms = time_budget_ms;
+        self
+    }
+
+    pub fn set_cancellation_token(mut self, token: &CancellationToken) -> GeneratorBuilder {
+        self.cancellation_token = Some(token.clone());
+        self
+    }
+
+    /// Discards choices that need more than `max_active_days` days on campus.
+    pub fn set_max_active_days(mut self, max_active_days: Option<u32>) -> GeneratorBuilder {
+        self.max_active_days = max_active_days;
+        self
+    }
+
+    /// Explains why the builder yields no choices: clashing mandatory subjects, subjects whose
+    /// commissions were all discarded and the choice level constraints that rejected the
+    /// remaining candidates, along with relaxations that would help.
+    pub fn diagnose(&self) -> Result<Diagnosis> {
+        Ok(serde_wasm_bindgen::to_value(&self.diagnosis())?.into())
+    }
+
+    /// The whole setup in the versioned `GeneratorConfig` shape, to be saved and restored with
+    /// `from_config`.
+    pub fn to_config(&self) -> Result<GeneratorConfigObject> {
+        self.config().to_js().map(Into::into)
+    }
+
+    pub fn from_config(
+        commissions: &Commissions,
+        config: GeneratorConfigObject,
+    ) -> Result<GeneratorBuilder> {
+        Self::with_config(
+            commissions.clone(),
+            &GeneratorConfig::from_js(config.into())?,
+        )
+    }
+
+    /// A compact URL-safe encoding of `to_config`, for share links.
+    pub fn to_share_string(&self) -> Result<String> {
+        self.config().to_share_string()
+    }
+
+    pub fn from_share_string(commissions: &Commissions, share: &str) -> Result<GeneratorBuilder> {
+        Self::with_config(
+            commissions.clone(),
+            &GeneratorConfig::from_share_string(share)?,
+        )
+    }
+
+    /// Counts the choices `build` would yield without serializing them.
+    pub fn count(&self, limit: Option<u32>) -> ChoiceCount {
+        let watchdog = self.watchdog();
+        watchdog.arm();
+        count_choices(self.choices(&watchdog), limit, &watchdog)
+    }
+
+    pub fn build(self) -> ChoiceGeneratorObject {
+        self.generator().into_js()
+    }
+
+    /// Enumerates every choice and yields only the `top_k` best scoring ones, best first. With a
+    /// time budget or cancellation token, only the candidates examined before the search stopped
+    /// are ranked and the generator starts with the status that stopped it.
+    pub fn build_ranked(self, top_k: u32) -> ChoiceGeneratorObject {
+        self.ranked_generator(top_k).into_js()
     }
 }
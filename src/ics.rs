@@ -0,0 +1,328 @@
+use enum_map::Enum;
+use itertools::Itertools;
+use js_sys::Date;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    error::{Result, SchedulerError},
+    generator::Choice,
+    serializer::{OptionInfo, Time},
+};
+
+/// Longest line allowed by RFC 5545, in octets, before folding.
+const MAX_LINE: usize = 75;
+
+const MS_PER_MINUTE: i64 = 60_000;
+const MS_PER_DAY: i64 = 86_400_000;
+
+#[wasm_bindgen(inline_js = r#"
+const formats = new Map();
+
+export function utc_offset_minutes(timeZone, time) {
+    let format = formats.get(timeZone);
+    if (format === undefined) {
+        format = new Intl.DateTimeFormat("en-US", {
+            timeZone,
+            hourCycle: "h23",
+            year: "numeric",
+            month: "numeric",
+            day: "numeric",
+            hour: "numeric",
+            minute: "numeric",
+            second: "numeric",
+        });
+        formats.set(timeZone, format);
+    }
+    const parts = format.formatToParts(new Date(time));
+    const part = type => Number(parts.find(p => p.type === type).value);
+    const local = Date.UTC(
+        part("year"), part("month") - 1, part("day"), part("hour"), part("minute"), part("second"),
+    );
+    return Math.round((local - Math.floor(time / 1000) * 1000) / 60000);
+}
+"#)]
+extern "C" {
+    #[wasm_bindgen(catch)]
+    fn utc_offset_minutes(time_zone: &str, time: f64) -> std::result::Result<i32, JsValue>;
+}
+
+/// A calendar date as days since 1970-01-01.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct CivilDate(i64);
+
+impl CivilDate {
+    /// Parses `YYYY-MM-DD`.
+    fn parse(date: &str) -> Result<Self> {
+        let invalid =
+            || SchedulerError::malformed_input(format!("\"{date}\" is not a YYYY-MM-DD date"));
+        let (year, month, day) = date
+            .splitn(3, '-')
+            .map(|part| part.parse::<i64>().ok())
+            .collect_tuple()
+            .ok_or_else(invalid)?;
+        let (year, month, day) = (
+            year.ok_or_else(invalid)?,
+            month.ok_or_else(invalid)?,
+            day.ok_or_else(invalid)?,
+        );
+        let date = Self::from_civil(year, month, day);
+        if !(1..=12).contains(&month) || date.civil() != (year, month, day) {
+            return Err(invalid());
+        }
+        Ok(date)
+    }
+
+    /// Days since the epoch of a proleptic Gregorian date.
+    fn from_civil(year: i64, month: i64, day: i64) -> Self {
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        Self(era * 146097 + day_of_era - 719468)
+    }
+
+    fn civil(self) -> (i64, i64, i64) {
+        let days = self.0 + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        (year, month, day)
+    }
+
+    /// Days since Monday.
+    fn weekday(self) -> i64 {
+        (self.0 + 3).rem_euclid(7)
+    }
+
+    fn format(self) -> String {
+        let (year, month, day) = self.civil();
+        format!("{year:04}{month:02}{day:02}")
+    }
+}
+
+/// Formats milliseconds since the epoch as an iCalendar `DATE-TIME`, without the UTC marker.
+fn format_date_time(ms: i64) -> String {
+    let seconds = ms.rem_euclid(MS_PER_DAY) / 1000;
+    format!(
+        "{}T{:02}{:02}{:02}",
+        CivilDate(ms.div_euclid(MS_PER_DAY)).format(),
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn format_offset(minutes: i32) -> String {
+    let sign = if minutes < 0 { '-' } else { '+' };
+    format!("{sign}{:02}{:02}", minutes.abs() / 60, minutes.abs() % 60)
+}
+
+fn format_time(time: Time) -> String {
+    format!("{:02}{:02}00", time.hour, time.minutes)
+}
+
+/// The current UTC time as an iCalendar `DATE-TIME`.
+fn now_stamp() -> String {
+    let now = Date::new_0();
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        now.get_utc_full_year(),
+        now.get_utc_month() + 1,
+        now.get_utc_date(),
+        now.get_utc_hours(),
+        now.get_utc_minutes(),
+        now.get_utc_seconds()
+    )
+}
+
+/// Accepts IANA names, which are valid property values without escaping.
+fn validate_timezone(timezone: &str) -> Result<()> {
+    let valid = !timezone.is_empty()
+        && timezone
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '-' | '+'));
+    if valid {
+        Ok(())
+    } else {
+        Err(SchedulerError::malformed_input(format!(
+            "{timezone:?} is not an IANA time zone name"
+        )))
+    }
+}
+
+/// Minutes `timezone` is ahead of UTC at `ms` milliseconds since the epoch.
+fn offset_at(timezone: &str, ms: i64) -> Result<i32> {
+    utc_offset_minutes(timezone, ms as f64).map_err(|_| {
+        SchedulerError::malformed_input(format!("{timezone:?} is not a known time zone"))
+    })
+}
+
+/// Milliseconds since the epoch of a local time in `timezone`.
+fn local_to_utc(timezone: &str, local: i64) -> Result<i64> {
+    let guess = local - offset_at(timezone, local)? as i64 * MS_PER_MINUTE;
+    Ok(local - offset_at(timezone, guess)? as i64 * MS_PER_MINUTE)
+}
+
+/// A `VTIMEZONE` with the offset the term starts in and every change until it ends.
+fn push_timezone(ics: &mut String, timezone: &str, start: CivilDate, end: CivilDate) -> Result<()> {
+    let mut time = (start.0 - 1) * MS_PER_DAY;
+    let initial = offset_at(timezone, time)?;
+    let mut offset = initial;
+    let mut changes = vec![];
+    while time < (end.0 + 2) * MS_PER_DAY {
+        let next = offset_at(timezone, time + MS_PER_DAY)?;
+        if next != offset {
+            let (mut before, mut after) = (time, time + MS_PER_DAY);
+            while after - before > MS_PER_MINUTE {
+                let middle = before + (after - before) / MS_PER_MINUTE / 2 * MS_PER_MINUTE;
+                if offset_at(timezone, middle)? == offset {
+                    before = middle;
+                } else {
+                    after = middle;
+                }
+            }
+            changes.push((after, offset, next));
+            offset = next;
+        }
+        time += MS_PER_DAY;
+    }
+
+    // The term starts in daylight time when the first change moves the clocks back.
+    let initial_daylight = matches!(changes.first(), Some((_, from, to)) if to < from);
+    push_line(ics, "BEGIN:VTIMEZONE");
+    push_line(ics, &format!("TZID:{timezone}"));
+    let components = std::iter::once((0, initial, initial, initial_daylight)).chain(
+        changes
+            .into_iter()
+            .map(|(at, from, to)| (at + from as i64 * MS_PER_MINUTE, from, to, to > from)),
+    );
+    for (local_start, from, to, daylight) in components {
+        let kind = if daylight { "DAYLIGHT" } else { "STANDARD" };
+        push_line(ics, &format!("BEGIN:{kind}"));
+        push_line(ics, &format!("DTSTART:{}", format_date_time(local_start)));
+        push_line(ics, &format!("TZOFFSETFROM:{}", format_offset(from)));
+        push_line(ics, &format!("TZOFFSETTO:{}", format_offset(to)));
+        push_line(ics, &format!("END:{kind}"));
+    }
+    push_line(ics, "END:VTIMEZONE");
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Appends a content line, folded so no line is longer than `MAX_LINE` octets.
+fn push_line(ics: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE {
+            ics.push_str("\r\n ");
+            width = 1;
+        }
+        ics.push(c);
+        width += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+/// Renders a choice as weekly recurring events in `timezone` through a `YYYY-MM-DD` term.
+#[wasm_bindgen]
+pub fn choice_to_ics(
+    choice: Choice,
+    term_start: &str,
+    term_end: &str,
+    timezone: &str,
+) -> Result<String> {
+    validate_timezone(timezone)?;
+    let info = OptionInfo::from_js(choice.into())?;
+    let (start, end) = (CivilDate::parse(term_start)?, CivilDate::parse(term_end)?);
+    if end < start {
+        return Err(SchedulerError::malformed_input(format!(
+            "The term ends ({term_end}) before it starts ({term_start})"
+        )));
+    }
+
+    let stamp = now_stamp();
+    // RFC 5545 wants UTC here when DTSTART has a TZID: the last local second of the term.
+    let last = local_to_utc(timezone, (end.0 + 1) * MS_PER_DAY - 1000)?;
+    let until = format!("{}Z", format_date_time(last));
+    let mut ics = String::new();
+    for line in [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//scheduler-wasm//Choice export//EN",
+        "CALSCALE:GREGORIAN",
+        &format!("X-WR-TIMEZONE:{timezone}"),
+    ] {
+        push_line(&mut ics, line);
+    }
+    push_timezone(&mut ics, timezone, start, end)?;
+
+    for (day, tasks) in info.week.iter() {
+        let offset = (day.into_usize() as i64 - start.weekday()).rem_euclid(7);
+        let first = CivilDate(start.0 + offset);
+        if first > end {
+            continue;
+        }
+        for task in tasks {
+            let subject = info.subjects.get(&task.subject);
+            let summary = match subject {
+                Some(subject) if !subject.commissions.is_empty() => {
+                    format!("{} ({})", subject.name, subject.commissions.join(", "))
+                }
+                Some(subject) => subject.name.clone(),
+                None => task.subject.to_string(),
+            };
+            let uid = format!(
+                "{}-{}-{}-{}@scheduler-wasm",
+                task.subject,
+                first.format(),
+                format_time(task.span.start),
+                format_time(task.span.end)
+            );
+
+            push_line(&mut ics, "BEGIN:VEVENT");
+            push_line(&mut ics, &format!("UID:{}", escape(&uid)));
+            push_line(&mut ics, &format!("DTSTAMP:{stamp}"));
+            push_line(
+                &mut ics,
+                &format!(
+                    "DTSTART;TZID={timezone}:{}T{}",
+                    first.format(),
+                    format_time(task.span.start)
+                ),
+            );
+            push_line(
+                &mut ics,
+                &format!(
+                    "DTEND;TZID={timezone}:{}T{}",
+                    first.format(),
+                    format_time(task.span.end)
+                ),
+            );
+            push_line(&mut ics, &format!("RRULE:FREQ=WEEKLY;UNTIL={until}"));
+            push_line(&mut ics, &format!("SUMMARY:{}", escape(&summary)));
+            let location: Vec<_> = task.classroom.iter().chain(&task.buildings).collect();
+            if !location.is_empty() {
+                push_line(
+                    &mut ics,
+                    &format!("LOCATION:{}", escape(&location.iter().join(", "))),
+                );
+            }
+            push_line(&mut ics, "END:VEVENT");
+        }
+    }
+    push_line(&mut ics, "END:VCALENDAR");
+    Ok(ics)
+}
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize, Serializer};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use crate::{Level, Semester};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "PeriodDescriptor[]")]
+    pub type PeriodDescriptorArray;
+
+    #[wasm_bindgen(typescript_type = "PlanDescriptor[]")]
+    pub type PlanDescriptorArray;
+}
+
+/// The `index.json` document listing what is published under the base URL.
+#[derive(Deserialize)]
+pub struct Index {
+    #[serde(default)]
+    pub periods: Vec<IndexPeriod>,
+    #[serde(default)]
+    pub plans: Vec<PlanDescriptor>,
+}
+
+#[derive(Deserialize)]
+pub struct IndexPeriod {
+    pub year: u32,
+    pub semester: String,
+    pub level: String,
+    #[serde(default, alias = "lastUpdated")]
+    pub last_updated: Option<String>,
+}
+
+fn serialize_discriminant<S: Serializer, T: Copy + Into<u32>>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u32((*value).into())
+}
+
+impl From<Semester> for u32 {
+    fn from(semester: Semester) -> Self {
+        semester as u32
+    }
+}
+
+impl From<Level> for u32 {
+    fn from(level: Level) -> Self {
+        level as u32
+    }
+}
+
+/// A semester, or its name as published when it isn't one of the configured names.
+#[derive(Debug, PartialEq, Serialize, Tsify)]
+#[serde(untagged)]
+pub enum PeriodSemester {
+    Known(#[serde(serialize_with = "serialize_discriminant")] Semester),
+    Unknown(String),
+}
+
+/// A level, or its name as published when it isn't one of the configured names.
+#[derive(Debug, PartialEq, Serialize, Tsify)]
+#[serde(untagged)]
+pub enum PeriodLevel {
+    Known(#[serde(serialize_with = "serialize_discriminant")] Level),
+    Unknown(String),
+}
+
+#[derive(Debug, PartialEq, Serialize, Tsify)]
+pub struct PeriodDescriptor {
+    pub year: u32,
+    pub semester: PeriodSemester,
+    pub level: PeriodLevel,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_updated: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Tsify)]
+pub struct PlanDescriptor {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
@@ -0,0 +1,64 @@
+use js_sys::{Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const IITERATOR: &'static str = r#"
+export interface ChoiceGenerator {
+    [Symbol.iterator](): Iterator<Choice>;
+    [Symbol.asyncIterator](): AsyncIterator<Choice>;
+}
+"#;
+
+/// Milliseconds the async iterator keeps the event loop busy before yielding to it.
+const ASYNC_YIELD_MS: u32 = 8;
+
+#[wasm_bindgen(inline_js = r#"
+export function install_iterator_protocols(instance, asyncYieldMs) {
+    const prototype = Object.getPrototypeOf(instance);
+    if (Object.prototype.hasOwnProperty.call(prototype, Symbol.iterator)) {
+        return;
+    }
+    prototype[Symbol.iterator] = function () {
+        return this;
+    };
+    prototype[Symbol.asyncIterator] = function () {
+        const generator = this;
+        let last = Date.now();
+        return {
+            next() {
+                if (Date.now() - last < asyncYieldMs) {
+                    return Promise.resolve(generator.next());
+                }
+                return new Promise((resolve, reject) => setTimeout(() => {
+                    last = Date.now();
+                    try {
+                        resolve(generator.next());
+                    } catch (e) {
+                        reject(e);
+                    }
+                }, 0));
+            },
+            [Symbol.asyncIterator]() {
+                return this;
+            },
+        };
+    };
+}
+"#)]
+extern "C" {
+    #[wasm_bindgen(js_name = install_iterator_protocols)]
+    fn install(instance: &JsValue, async_yield_ms: u32);
+}
+
+/// An `IteratorResult` object.
+pub fn iterator_result(value: JsValue, done: bool) -> JsValue {
+    let result = Object::new();
+    Reflect::set(&result, &"value".into(), &value).unwrap();
+    Reflect::set(&result, &"done".into(), &done.into()).unwrap();
+    result.into()
+}
+
+/// Attaches the symbol keyed iterator methods, which wasm-bindgen can't export.
+pub fn install_iterator_protocols(instance: &JsValue) {
+    install(instance, ASYNC_YIELD_MS);
+}
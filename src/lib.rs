@@ -1,12 +1,29 @@
 #![feature(let_chains)]
+mod cache;
+mod cancellation;
 mod commissions;
+mod config;
+mod constraints;
+mod declarations;
+mod details;
+mod diagnostics;
+mod error;
+mod export;
 mod generator;
+mod ics;
+mod index;
+mod iterator;
 mod plan;
+mod schedule;
+mod scoring;
 mod serializer;
 #[cfg(test)]
 mod tests;
+mod transport;
 mod utils;
+mod worker;
 
+use error::{Result, SchedulerError};
 use js_sys::{Array, JsString};
 use scheduler::models::Code;
 use wasm_bindgen::prelude::*;
@@ -23,9 +40,51 @@ extern "C" {
 }
 
 #[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Semester {
     First,
     Second,
+    Summer,
+    Winter,
+    Intensive,
+}
+
+impl Semester {
+    const ALL: [Semester; 5] = [
+        Semester::First,
+        Semester::Second,
+        Semester::Summer,
+        Semester::Winter,
+        Semester::Intensive,
+    ];
+
+    fn default_name(&self) -> &'static str {
+        match self {
+            Semester::First => "FirstSemester",
+            Semester::Second => "SecondSemester",
+            Semester::Summer => "Summer",
+            Semester::Winter => "Winter",
+            Semester::Intensive => "Intensive",
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Level {
+    Graduate,
+    Postgraduate,
+}
+
+impl Level {
+    const ALL: [Level; 2] = [Level::Graduate, Level::Postgraduate];
+
+    fn default_name(&self) -> &'static str {
+        match self {
+            Level::Graduate => "GRADUATE",
+            Level::Postgraduate => "POSTGRADUATE",
+        }
+    }
 }
 
 #[wasm_bindgen]
@@ -53,20 +112,32 @@ impl SubjectInfo {
     }
 }
 
+fn parse_code(code: &str) -> Result<Code> {
+    code.parse().map_err(|_| SchedulerError::invalid_code(code))
+}
+
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(typescript_type = "string[]")]
     pub type StringArray;
 
-    #[wasm_bindgen(typescript_type = "[[string, string], [string, string]][]")]
+    #[wasm_bindgen(typescript_type = "[CommissionRef, CommissionRef][]")]
     pub type CollisionExceptions;
 }
 
-impl From<StringArray> for Vec<String> {
-    fn from(sa: StringArray) -> Self {
+impl TryFrom<StringArray> for Vec<String> {
+    type Error = SchedulerError;
+
+    fn try_from(sa: StringArray) -> Result<Self> {
+        if !Array::is_array(&sa) {
+            return Err(SchedulerError::malformed_input("Expected a string array"));
+        }
         Array::from(&sa)
             .iter()
-            .map(|v| v.as_string().expect("Must be a string array"))
+            .map(|v| {
+                v.as_string()
+                    .ok_or_else(|| SchedulerError::malformed_input("Must be a string array"))
+            })
             .collect()
     }
 }
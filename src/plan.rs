@@ -1,7 +1,19 @@
-use crate::{StringArray, SubjectInfo};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    fmt::Write,
+};
+
+use crate::{
+    commissions::Commissions,
+    error::{Result, SchedulerError},
+    parse_code, StringArray, SubjectInfo,
+};
 use itertools::Itertools;
 use js_sys::{Object, Reflect};
-use scheduler::json_parser::{CareerPlan, Entry, SubjectEntry};
+use scheduler::{
+    json_parser::{CareerPlan, Entry, SubjectEntry},
+    models::Code,
+};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -13,6 +25,189 @@ impl SubjectPlan {
     pub fn new(data: CareerPlan) -> Self {
         Self { data }
     }
+
+    /// Codes of the subjects not in `completed` whose dependencies are all in `completed`, each
+    /// listed once however many times the plan lists it. When `commissions` is given, only the
+    /// subjects offered in it are kept.
+    pub fn eligible(
+        &self,
+        completed: &HashSet<Code>,
+        commissions: Option<&Commissions>,
+    ) -> Vec<Code> {
+        get_subjects(&self.data)
+            .filter(|s| {
+                !completed.contains(&s.code) && s.dependencies.iter().all(|d| completed.contains(d))
+            })
+            .filter(|s| match commissions {
+                Some(commissions) => commissions.find_subject_by_code(s.code).is_some(),
+                None => true,
+            })
+            .map(|s| s.code)
+            .collect()
+    }
+
+    fn find_subject(&self, code: Code) -> Option<&SubjectEntry> {
+        get_subjects(&self.data).find(|s| s.code == code)
+    }
+
+    /// Subjects that list each code as a direct dependency, each once even if it lists the code
+    /// more than once.
+    fn reverse_dependencies(&self) -> HashMap<Code, BTreeSet<Code>> {
+        let mut dependents: HashMap<_, BTreeSet<_>> = HashMap::new();
+        for subject in get_subjects(&self.data) {
+            for &dependency in &subject.dependencies {
+                dependents
+                    .entry(dependency)
+                    .or_default()
+                    .insert(subject.code);
+            }
+        }
+        dependents
+    }
+
+    fn transitive_dependents(&self, code: Code) -> Vec<Code> {
+        let dependents = self.reverse_dependencies();
+        reachable(code, |c| {
+            dependents.get(&c).into_iter().flatten().copied().collect()
+        })
+    }
+
+    /// Subjects grouped by section and term. A subject listed more than once is only placed
+    /// where it first appears.
+    fn clusters(&self) -> Vec<SectionCluster<'_>> {
+        let mut placed = HashSet::new();
+        self.data
+            .sections
+            .iter()
+            .enumerate()
+            .map(|(i, section)| {
+                let mut terms = section
+                    .terms
+                    .iter()
+                    .map(|t| TermCluster {
+                        label: Some(format!("Year {}, period {}", t.term.year, t.term.period)),
+                        subjects: place(&mut placed, &t.entries),
+                    })
+                    .collect_vec();
+                terms.push(TermCluster {
+                    label: None,
+                    subjects: place(&mut placed, &section.without_term),
+                });
+                terms.retain(|t| !t.subjects.is_empty());
+                SectionCluster {
+                    label: format!("Section {}", i + 1),
+                    terms,
+                }
+            })
+            .filter(|s| !s.terms.is_empty())
+            .collect()
+    }
+
+    /// Every dependency edge of the plan, from the dependency to the subject that needs it, each
+    /// once.
+    fn edges(&self) -> impl Iterator<Item = (Code, Code)> + '_ {
+        get_subjects(&self.data).flat_map(|s| {
+            s.dependencies
+                .iter()
+                .unique()
+                .map(move |&dependency| (dependency, s.code))
+        })
+    }
+
+    /// How each subject of the plan should be highlighted for a student who passed
+    /// `completed_codes`. Completed codes the plan doesn't list are left out.
+    fn highlights(&self, completed_codes: Option<StringArray>) -> Result<HashMap<Code, Highlight>> {
+        let Some(completed_codes) = completed_codes else {
+            return Ok(HashMap::new());
+        };
+        let completed = parse_code_set(completed_codes)?;
+        let eligible = self
+            .eligible(&completed, None)
+            .into_iter()
+            .map(|code| (code, Highlight::Eligible));
+        Ok(completed
+            .iter()
+            .filter(|&&code| self.find_subject(code).is_some())
+            .map(|&code| (code, Highlight::Completed))
+            .chain(eligible)
+            .collect())
+    }
+}
+
+/// Every code reachable from `start` through `edges`, excluding `start`, in breadth-first order.
+fn reachable(start: Code, edges: impl Fn(Code) -> Vec<Code>) -> Vec<Code> {
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    let mut reached = vec![];
+    while let Some(code) = queue.pop_front() {
+        for next in edges(code) {
+            if seen.insert(next) {
+                reached.push(next);
+                queue.push_back(next);
+            }
+        }
+    }
+    reached
+}
+
+fn codes_to_array(codes: impl IntoIterator<Item = Code>) -> StringArray {
+    codes
+        .into_iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Fill colors of highlighted subjects in rendered graphs.
+const COMPLETED_COLOR: &str = "#c8e6c9";
+const ELIGIBLE_COLOR: &str = "#fff9c4";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Highlight {
+    Completed,
+    Eligible,
+}
+
+/// Subjects of a term, or of a section outside any term.
+struct TermCluster<'a> {
+    label: Option<String>,
+    subjects: Vec<&'a SubjectEntry>,
+}
+
+struct SectionCluster<'a> {
+    label: String,
+    terms: Vec<TermCluster<'a>>,
+}
+
+/// Subjects among `entries` not placed in a cluster yet.
+fn place<'a>(placed: &mut HashSet<Code>, entries: &'a [Entry]) -> Vec<&'a SubjectEntry> {
+    entries
+        .iter()
+        .filter_map(|e| match e {
+            Entry::Subject(subject) => Some(subject),
+            _ => None,
+        })
+        .filter(|s| placed.insert(s.code))
+        .collect()
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn mermaid_escape(text: &str) -> String {
+    text.replace('"', "#quot;")
+}
+
+fn mermaid_id(code: Code) -> String {
+    format!("s{}", code.to_string().replace('.', "_"))
+}
+
+fn parse_code_set(codes: StringArray) -> Result<HashSet<Code>> {
+    Vec::<String>::try_from(codes)?
+        .iter()
+        .map(|c| parse_code(c))
+        .collect()
 }
 
 fn get_subjects(career_plan: &CareerPlan) -> impl Iterator<Item = &SubjectEntry> {
@@ -37,15 +232,21 @@ fn get_subjects(career_plan: &CareerPlan) -> impl Iterator<Item = &SubjectEntry>
 
 #[wasm_bindgen]
 impl SubjectPlan {
-    pub fn get_subject_dependencies(&self, code: String) -> Option<StringArray> {
-        let code = code.parse().unwrap();
-        get_subjects(&self.data).find(|s| s.code == code).map(|s| {
+    pub fn from_json(json: &str) -> Result<SubjectPlan> {
+        serde_json::from_str::<CareerPlan>(json)
+            .map(SubjectPlan::new)
+            .map_err(SchedulerError::json)
+    }
+
+    pub fn get_subject_dependencies(&self, code: String) -> Result<Option<StringArray>> {
+        let code = parse_code(&code)?;
+        Ok(get_subjects(&self.data).find(|s| s.code == code).map(|s| {
             s.dependencies
                 .iter()
                 .map(|c| c.to_string())
                 .collect::<Vec<_>>()
                 .into()
-        })
+        }))
     }
 
     pub fn get_subjects(&self) -> StringArray {
@@ -55,20 +256,186 @@ impl SubjectPlan {
             .into()
     }
 
-    pub fn get_subject_info(&self, code: String) -> Option<SubjectInfo> {
-        let code = code.parse().unwrap();
-        get_subjects(&self.data)
+    /// Codes of the subjects a student who passed `completed_codes` may take next.
+    pub fn eligible_subjects(&self, completed_codes: StringArray) -> Result<StringArray> {
+        let completed = parse_code_set(completed_codes)?;
+        Ok(codes_to_array(self.eligible(&completed, None)))
+    }
+
+    /// Like `eligible_subjects`, keeping only the subjects offered in `commissions`. It is a
+    /// separate method because wasm-bindgen can't receive an `Option<&Commissions>` from JS.
+    pub fn eligible_offered_subjects(
+        &self,
+        completed_codes: StringArray,
+        commissions: &Commissions,
+    ) -> Result<StringArray> {
+        let completed = parse_code_set(completed_codes)?;
+        Ok(codes_to_array(self.eligible(&completed, Some(commissions))))
+    }
+
+    /// Every subject that must be passed before `code`, directly or through other dependencies.
+    pub fn all_prerequisites(&self, code: String) -> Result<Option<StringArray>> {
+        let code = parse_code(&code)?;
+        if self.find_subject(code).is_none() {
+            return Ok(None);
+        }
+        let prerequisites = reachable(code, |c| {
+            self.find_subject(c)
+                .map(|s| s.dependencies.clone())
+                .unwrap_or_default()
+        });
+        Ok(Some(codes_to_array(prerequisites)))
+    }
+
+    /// Subjects that list `code` as a direct dependency, in code order.
+    pub fn dependents(&self, code: String) -> Result<Option<StringArray>> {
+        let code = parse_code(&code)?;
+        if self.find_subject(code).is_none() {
+            return Ok(None);
+        }
+        let dependents = self
+            .reverse_dependencies()
+            .remove(&code)
+            .unwrap_or_default();
+        Ok(Some(codes_to_array(dependents)))
+    }
+
+    /// How many subjects need `code`, directly or transitively. Subjects with high counts are
+    /// bottlenecks of the plan.
+    pub fn unlock_count(&self, code: String) -> Result<Option<u32>> {
+        let code = parse_code(&code)?;
+        if self.find_subject(code).is_none() {
+            return Ok(None);
+        }
+        Ok(Some(self.transitive_dependents(code).len() as u32))
+    }
+
+    /// Renders the dependency graph as Graphviz DOT, with a cluster per section and term. When
+    /// `completed_codes` is given, completed and eligible subjects are filled in.
+    pub fn to_dot(&self, completed_codes: Option<StringArray>) -> Result<String> {
+        let highlights = self.highlights(completed_codes)?;
+        let mut dot = String::from("digraph plan {\n    rankdir=LR;\n    node [shape=box];\n");
+        for (i, section) in self.clusters().iter().enumerate() {
+            writeln!(dot, "    subgraph cluster_{i} {{").unwrap();
+            writeln!(dot, "        label=\"{}\";", dot_escape(&section.label)).unwrap();
+            for (j, term) in section.terms.iter().enumerate() {
+                let indent = if let Some(label) = &term.label {
+                    writeln!(dot, "        subgraph cluster_{i}_{j} {{").unwrap();
+                    writeln!(dot, "            label=\"{}\";", dot_escape(label)).unwrap();
+                    "            "
+                } else {
+                    "        "
+                };
+                for subject in &term.subjects {
+                    let style = match highlights.get(&subject.code) {
+                        Some(Highlight::Completed) => {
+                            format!(", style=filled, fillcolor=\"{COMPLETED_COLOR}\"")
+                        }
+                        Some(Highlight::Eligible) => {
+                            format!(", style=filled, fillcolor=\"{ELIGIBLE_COLOR}\"")
+                        }
+                        None => String::new(),
+                    };
+                    writeln!(
+                        dot,
+                        "{indent}\"{}\" [label=\"{}\\n{}\"{style}];",
+                        subject.code,
+                        subject.code,
+                        dot_escape(&subject.name)
+                    )
+                    .unwrap();
+                }
+                if term.label.is_some() {
+                    dot.push_str("        }\n");
+                }
+            }
+            dot.push_str("    }\n");
+        }
+        for (dependency, code) in self.edges() {
+            writeln!(dot, "    \"{dependency}\" -> \"{code}\";").unwrap();
+        }
+        dot.push_str("}\n");
+        Ok(dot)
+    }
+
+    /// Renders the dependency graph as a Mermaid flowchart, with a subgraph per section and term.
+    /// When `completed_codes` is given, completed and eligible subjects are filled in.
+    pub fn to_mermaid(&self, completed_codes: Option<StringArray>) -> Result<String> {
+        let highlights = self.highlights(completed_codes)?;
+        let mut mermaid = String::from("flowchart LR\n");
+        for (i, section) in self.clusters().iter().enumerate() {
+            writeln!(
+                mermaid,
+                "    subgraph section_{i}[\"{}\"]",
+                mermaid_escape(&section.label)
+            )
+            .unwrap();
+            for (j, term) in section.terms.iter().enumerate() {
+                let indent = if let Some(label) = &term.label {
+                    writeln!(
+                        mermaid,
+                        "        subgraph term_{i}_{j}[\"{}\"]",
+                        mermaid_escape(label)
+                    )
+                    .unwrap();
+                    "            "
+                } else {
+                    "        "
+                };
+                for subject in &term.subjects {
+                    writeln!(
+                        mermaid,
+                        "{indent}{}[\"{}<br/>{}\"]",
+                        mermaid_id(subject.code),
+                        subject.code,
+                        mermaid_escape(&subject.name)
+                    )
+                    .unwrap();
+                }
+                if term.label.is_some() {
+                    mermaid.push_str("        end\n");
+                }
+            }
+            mermaid.push_str("    end\n");
+        }
+        for (dependency, code) in self.edges() {
+            writeln!(
+                mermaid,
+                "    {} --> {}",
+                mermaid_id(dependency),
+                mermaid_id(code)
+            )
+            .unwrap();
+        }
+        if !highlights.is_empty() {
+            writeln!(mermaid, "    classDef completed fill:{COMPLETED_COLOR}").unwrap();
+            writeln!(mermaid, "    classDef eligible fill:{ELIGIBLE_COLOR}").unwrap();
+            for (&code, highlight) in highlights.iter().sorted_by_key(|(&code, _)| code) {
+                let class = match highlight {
+                    Highlight::Completed => "completed",
+                    Highlight::Eligible => "eligible",
+                };
+                writeln!(mermaid, "    class {} {class}", mermaid_id(code)).unwrap();
+            }
+        }
+        Ok(mermaid)
+    }
+
+    pub fn get_subject_info(&self, code: String) -> Result<Option<SubjectInfo>> {
+        let code = parse_code(&code)?;
+        Ok(get_subjects(&self.data)
             .find(|s| s.code == code)
             .map(|s| SubjectInfo {
                 code: s.code,
                 name: s.name.clone(),
                 credits: s.credits,
-            })
+            }))
     }
 
-    pub fn get_subject_terms(&self, code: String) -> Vec<Object> {
-        let code = code.parse().unwrap();
-        self.data
+    pub fn get_subject_terms(&self, code: String) -> Result<Vec<Object>> {
+        let code = parse_code(&code)?;
+        Ok(self
+            .data
             .sections
             .iter()
             .flat_map(|s| {
@@ -92,6 +459,6 @@ impl SubjectPlan {
                 Reflect::set(&js_term, &"period".into(), &term.period.into()).unwrap();
                 js_term
             })
-            .collect_vec()
+            .collect_vec())
     }
 }
@@ -0,0 +1,99 @@
+use enum_map::Enum;
+use scheduler::models::{Combinable, Day, DaysOfTheWeek, SubjectCommision, Time, Week};
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+
+/// Mirrors [`DaysOfTheWeek`] so days can be read from JS with the same names `Choice` uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Enum, Serialize, Deserialize, Tsify)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl From<Weekday> for DaysOfTheWeek {
+    fn from(day: Weekday) -> Self {
+        match day {
+            Weekday::Monday => DaysOfTheWeek::Monday,
+            Weekday::Tuesday => DaysOfTheWeek::Tuesday,
+            Weekday::Wednesday => DaysOfTheWeek::Wednesday,
+            Weekday::Thursday => DaysOfTheWeek::Thursday,
+            Weekday::Friday => DaysOfTheWeek::Friday,
+            Weekday::Saturday => DaysOfTheWeek::Saturday,
+            Weekday::Sunday => DaysOfTheWeek::Sunday,
+        }
+    }
+}
+
+pub fn minutes(time: Time) -> u32 {
+    time.hour as u32 * 60 + time.minutes as u32
+}
+
+/// Combines the schedules of every commission of a choice into a single week.
+pub fn merge_week<'a>(commissions: impl IntoIterator<Item = &'a SubjectCommision>) -> Week {
+    commissions
+        .into_iter()
+        .map(|c| &c.schedule)
+        .fold(Week::empty(), |a, b| Week::combine(&a, b))
+}
+
+/// A task reduced to what the generator reasons about, in minutes since midnight.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slot {
+    pub start: u32,
+    pub end: u32,
+    pub buildings: Vec<String>,
+}
+
+impl Slot {
+    /// Minutes shared with the `[start, end)` range.
+    pub fn overlap(&self, start: u32, end: u32) -> u32 {
+        self.end.min(end).saturating_sub(self.start.max(start))
+    }
+}
+
+/// The tasks of a day, sorted by start time.
+pub fn day_slots(day: &Day) -> Vec<Slot> {
+    let mut slots: Vec<_> = day
+        .tasks
+        .iter()
+        .map(|task| Slot {
+            start: minutes(task.span.start),
+            end: minutes(task.span.end),
+            buildings: task.info.buildings.iter().map(|b| b.name.clone()).collect(),
+        })
+        .collect();
+    slots.sort_by_key(|s| (s.start, s.end));
+    slots
+}
+
+/// Days of `week` with at least one task.
+pub fn active_days(week: &Week) -> impl Iterator<Item = DaysOfTheWeek> + '_ {
+    week.days
+        .iter()
+        .filter(|(_, day)| !day.tasks.is_empty())
+        .map(|(day, _)| day)
+}
+
+pub fn credits(commissions: &[SubjectCommision]) -> u32 {
+    commissions
+        .iter()
+        .filter_map(|c| c.subject.upgrade())
+        .map(|s| s.borrow().credits as u32)
+        .sum()
+}
+
+/// Whether any task of `a` shares time with a task of `b` on the same day.
+pub fn overlaps(a: &Week, b: &Week) -> bool {
+    a.days.iter().any(|(day, tasks)| {
+        let others = day_slots(&b.days[day]);
+        day_slots(tasks)
+            .iter()
+            .any(|slot| others.iter().any(|o| slot.overlap(o.start, o.end) > 0))
+    })
+}
@@ -0,0 +1,175 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use scheduler::models::SubjectCommision;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    constraints::{blocked_minutes, BlockedSlot},
+    schedule::{credits, day_slots, merge_week},
+};
+
+/// Weights of the metrics summed into a score. Lower scores rank first.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Tsify)]
+#[serde(rename = "ScoringWeightsInit", default)]
+pub struct ScoringWeights {
+    pub idle_gap_minutes: f64,
+    pub active_days: f64,
+    pub earliest_start: f64,
+    pub latest_end: f64,
+    pub building_changes: f64,
+    pub credits: f64,
+    pub blocked_minutes: f64,
+}
+
+/// `ScoringWeights` as read from a plain object, where every weight left out keeps its default.
+pub type ScoringWeightsInit = ScoringWeights;
+
+impl Default for ScoringWeights {
+    fn default() -> Self {
+        Self {
+            idle_gap_minutes: 1.,
+            active_days: 60.,
+            earliest_start: 0.,
+            latest_end: 0.,
+            building_changes: 30.,
+            credits: 0.,
+            blocked_minutes: 10.,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl ScoringWeights {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> ScoringWeights {
+        Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, Tsify)]
+pub struct Score {
+    pub total: f64,
+    /// Minutes of class inside soft blocked slots.
+    pub blocked_minutes: u32,
+    /// Minutes between consecutive tasks of the same day.
+    pub idle_gap_minutes: u32,
+    pub active_days: u32,
+    /// Earliest start of the week, in minutes since midnight.
+    pub earliest_start: u32,
+    /// Latest end of the week, in minutes since midnight.
+    pub latest_end: u32,
+    /// Consecutive tasks of the same day held in different buildings.
+    pub building_changes: u32,
+    pub credits: u32,
+}
+
+/// Scores choices with a set of weights, penalizing classes in soft blocked slots.
+#[derive(Debug, Clone, Default)]
+pub struct Scorer {
+    pub weights: ScoringWeights,
+    pub blocked_slots: Vec<BlockedSlot>,
+}
+
+impl Scorer {
+    pub fn score(&self, commissions: &[SubjectCommision]) -> Score {
+        let week = merge_week(commissions);
+        let weights = &self.weights;
+        let mut score = Score {
+            earliest_start: u32::MAX,
+            credits: credits(commissions),
+            blocked_minutes: blocked_minutes(&week, &self.blocked_slots),
+            ..Default::default()
+        };
+
+        for (_, day) in week.days.iter() {
+            let slots = day_slots(day);
+            let Some(first) = slots.first() else {
+                continue;
+            };
+            score.active_days += 1;
+            score.earliest_start = score.earliest_start.min(first.start);
+
+            let mut end = first.end;
+            for pair in slots.windows(2) {
+                let (previous, next) = (&pair[0], &pair[1]);
+                score.idle_gap_minutes += next.start.saturating_sub(end);
+                end = end.max(next.end);
+                if !previous.buildings.is_empty()
+                    && !next.buildings.is_empty()
+                    && previous.buildings != next.buildings
+                {
+                    score.building_changes += 1;
+                }
+            }
+            score.latest_end = score.latest_end.max(end);
+        }
+        if score.active_days == 0 {
+            score.earliest_start = 0;
+        }
+
+        score.total = weights.idle_gap_minutes * score.idle_gap_minutes as f64
+            + weights.active_days * score.active_days as f64
+            + weights.earliest_start * score.earliest_start as f64
+            + weights.latest_end * score.latest_end as f64
+            + weights.building_changes * score.building_changes as f64
+            + weights.credits * score.credits as f64
+            + weights.blocked_minutes * score.blocked_minutes as f64;
+        score
+    }
+}
+
+struct Ranked {
+    total: f64,
+    /// Position in the enumeration, so equal totals keep the order they were found in.
+    index: usize,
+    choice: Vec<Option<SubjectCommision>>,
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.total
+            .total_cmp(&other.total)
+            .then(self.index.cmp(&other.index))
+    }
+}
+
+/// Keeps the `top_k` lowest scoring choices, returned best first. Ties keep the earliest choices.
+pub fn rank(
+    choices: impl Iterator<Item = Vec<Option<SubjectCommision>>>,
+    scorer: &Scorer,
+    top_k: usize,
+) -> Vec<Vec<Option<SubjectCommision>>> {
+    let mut best = BinaryHeap::with_capacity(top_k + 1);
+    for (index, choice) in choices.enumerate() {
+        let commissions: Vec<_> = choice.iter().flatten().cloned().collect();
+        best.push(Ranked {
+            total: scorer.score(&commissions).total,
+            index,
+            choice,
+        });
+        if best.len() > top_k {
+            best.pop();
+        }
+    }
+    best.into_sorted_vec()
+        .into_iter()
+        .map(|ranked| ranked.choice)
+        .collect()
+}
@@ -2,22 +2,48 @@ use enum_map::EnumMap;
 use itertools::Itertools;
 use scheduler::{
     json_parser::Code,
-    models::{Combinable, DaysOfTheWeek, SubjectCommision, Week},
+    models::{DaysOfTheWeek, SubjectCommision},
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use tsify::Tsify;
 
-#[derive(Clone, Serialize)]
-struct Subject {
-    name: String,
-    credits: u32,
-    commissions: Vec<String>,
+use wasm_bindgen::JsValue;
+
+use crate::{
+    details::DetailsTable,
+    error::{Result, SchedulerError},
+    schedule::{merge_week, minutes, Weekday},
+    scoring::Score,
+};
+
+#[derive(Clone, Serialize, Deserialize, Tsify)]
+pub struct Subject {
+    pub name: String,
+    pub credits: u32,
+    pub commissions: Vec<String>,
+    /// Details of each commission in `commissions` that the source document describes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commission_details: Vec<CommissionInfo>,
 }
 
-#[derive(Clone, Copy, Serialize)]
-struct Time {
-    hour: u8,
-    minutes: u8,
+#[derive(Clone, Serialize, Deserialize, Tsify)]
+pub struct CommissionInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub teachers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enrolled: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+pub struct Time {
+    pub hour: u8,
+    pub minutes: u8,
 }
 
 impl From<scheduler::models::Time> for Time {
@@ -26,10 +52,16 @@ impl From<scheduler::models::Time> for Time {
     }
 }
 
-#[derive(Clone, Copy, Serialize)]
-struct Span {
-    start: Time,
-    end: Time,
+impl From<Time> for scheduler::models::Time {
+    fn from(Time { hour, minutes }: Time) -> Self {
+        Self { hour, minutes }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Tsify)]
+pub struct Span {
+    pub start: Time,
+    pub end: Time,
 }
 
 impl From<scheduler::models::Span> for Span {
@@ -41,18 +73,73 @@ impl From<scheduler::models::Span> for Span {
     }
 }
 
-#[derive(Clone, Serialize)]
-struct Task {
-    subject: Code,
-    buildings: Vec<String>,
-    //classroom: String,
-    span: Span,
+#[derive(Clone, Serialize, Deserialize, Tsify)]
+pub struct Task {
+    pub subject: Code,
+    pub buildings: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classroom: Option<String>,
+    pub span: Span,
 }
 
-#[derive(Clone, Serialize)]
+/// A choice as handed out to JS. serde_wasm_bindgen turns maps into JS `Map`s.
+#[derive(Clone, Serialize, Deserialize, Tsify)]
+#[serde(rename = "Choice")]
 pub struct OptionInfo {
-    subjects: HashMap<Code, Subject>,
-    week: EnumMap<DaysOfTheWeek, Vec<Task>>,
+    pub subjects: HashMap<Code, Subject>,
+    pub week: EnumMap<Weekday, Vec<Task>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<Score>,
+}
+
+impl OptionInfo {
+    pub fn with_score(mut self, score: Score) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    /// Adds what `details` knows about the commissions of the choice and the classroom of each
+    /// task.
+    pub fn with_details(mut self, details: &DetailsTable) -> Self {
+        let lookup = |code: Code, name: &String| details.get(&(code, name.clone()));
+        for (&code, subject) in self.subjects.iter_mut() {
+            subject.commission_details = subject
+                .commissions
+                .iter()
+                .filter_map(|name| {
+                    let commission = lookup(code, name)?;
+                    Some(CommissionInfo {
+                        name: name.clone(),
+                        id: commission.id.clone(),
+                        teachers: commission.teachers.clone(),
+                        quota: commission.quota,
+                        enrolled: commission.enrolled,
+                    })
+                })
+                .collect();
+        }
+        for (day, tasks) in self.week.iter_mut() {
+            for task in tasks {
+                let Some(subject) = self.subjects.get(&task.subject) else {
+                    continue;
+                };
+                let start = minutes(task.span.start.into());
+                task.classroom = subject
+                    .commissions
+                    .iter()
+                    .filter_map(|name| lookup(task.subject, name)?.classroom(day, start))
+                    .next()
+                    .map(str::to_owned);
+            }
+        }
+        self
+    }
+
+    /// Reads back a `Choice` handed out to JS.
+    pub fn from_js(choice: JsValue) -> Result<Self> {
+        serde_wasm_bindgen::from_value(choice)
+            .map_err(|e| SchedulerError::malformed_input(format!("Invalid choice: {e}")))
+    }
 }
 
 impl From<Vec<SubjectCommision>> for OptionInfo {
@@ -68,32 +155,35 @@ impl From<Vec<SubjectCommision>> for OptionInfo {
                         commissions: c.names.clone(),
                         name: s.name.clone(),
                         credits: s.credits as u32,
+                        commission_details: vec![],
                     },
                 )
             })
             .collect();
 
-        let week = commissions
-            .iter()
-            .map(|c| &c.schedule)
-            .fold(Week::empty(), |a, b| Week::combine(&a, b))
-            .days
-            .map(|_, scheduler::models::Day { tasks, .. }| {
-                tasks
-                    .iter()
-                    .map(|task| Task {
-                        subject: task.info.subject.upgrade().unwrap().borrow().code,
-                        span: task.span.into(),
-                        buildings: task
-                            .info
-                            .buildings
-                            .iter()
-                            .map(|b| b.name.clone())
-                            .collect_vec(),
-                    })
-                    .collect()
-            });
+        let days = merge_week(&commissions).days;
+        let week = EnumMap::from_fn(|day: Weekday| {
+            days[DaysOfTheWeek::from(day)]
+                .tasks
+                .iter()
+                .map(|task| Task {
+                    subject: task.info.subject.upgrade().unwrap().borrow().code,
+                    classroom: None,
+                    span: task.span.into(),
+                    buildings: task
+                        .info
+                        .buildings
+                        .iter()
+                        .map(|b| b.name.clone())
+                        .collect_vec(),
+                })
+                .collect()
+        });
 
-        Self { subjects, week }
+        Self {
+            subjects,
+            week,
+            score: None,
+        }
     }
 }
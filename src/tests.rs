@@ -1,58 +1,1185 @@
 #![cfg(target_arch = "wasm32")]
 
-use crate::{get_subject_info, load_from_api};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
+    rc::Rc,
+};
+
+use js_sys::{Array, Reflect};
+use tsify::Tsify;
+use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::JsFuture;
+
+use crate::{
+    api::{Api, ApiConfig},
+    cancellation::{CancellationToken, GeneratorStatus},
+    commissions::Commissions,
+    config::GeneratorConfig,
+    constraints::BlockedSlot,
+    diagnostics::{Clash, Constraint, DiagnosisInfo, PrunedSubject},
+    error::{ErrorKind, SchedulerError},
+    export::{choice_to_csv, choice_to_svg, SvgConfig},
+    generator::{BlockedSlots, ChoiceGenerator, GeneratorBuilder},
+    ics::choice_to_ics,
+    index::{PeriodDescriptor, PeriodLevel, PeriodSemester, PlanDescriptor},
+    plan::SubjectPlan,
+    schedule::Weekday,
+    scoring::{Score, ScoringWeights},
+    serializer::{CommissionInfo, OptionInfo, Span, Subject, Task, Time},
+    transport::{FixtureTransport, RawRequest, RawResponse, Transport, TransportFuture},
+    worker::WorkerPool,
+    StringArray,
+};
 
 extern crate wasm_bindgen_test;
 use wasm_bindgen_test::*;
 
 wasm_bindgen_test_configure!(run_in_browser);
 
-#[wasm_bindgen_test]
-async fn test_get_from_api() {
-    //let server = MockServer::start();
-
-    //let api_mock = server.mock(|when, then| {
-    //when.method(GET)
-    //.path("/api")
-    //.query_param("year", "2022")
-    //.query_param("period", "SecondSemester");
-    //then.status(200)
-    //.header("content-type", "application/json")
-    //.body(
-    //r#"
-    //{
-    //"courseCommissions": {
-    //"courseCommission": [
-    //{
-    //"subjectCode": "00.00",
-    //"subjectName": "Test subject",
-    //"subjectType": "NORMAL",
-    //"courseStart": "01/01/2001",
-    //"courseEnd": "01/01/2001",
-    //"commissionName": "TEST",
-    //"commissionId": "12345",
-    //"quota": "30",
-    //"enrolledStudents": "1",
-    //"courseCommissionTimes": [],
-    //}
-    //]
-    //}
-    //}
-    //"#,
-    //);
-    //});
-
-    //load_from_api(server.url("/api"), 2022, crate::Semester::Second).await;
-    load_from_api(
+const COMMISSIONS: &str = r#"
+{
+    "courseCommissions": {
+        "courseCommission": [
+            {
+                "subjectCode": "00.00",
+                "subjectName": "Test subject",
+                "subjectType": "NORMAL",
+                "courseStart": "01/01/2001",
+                "courseEnd": "01/01/2001",
+                "commissionName": "TEST",
+                "commissionId": "12345",
+                "quota": "30",
+                "enrolledStudents": "1",
+                "courseCommissionTimes": []
+            }
+        ]
+    }
+}
+"#;
+
+const TWO_COMMISSIONS: &str = r#"
+{
+    "courseCommissions": {
+        "courseCommission": [
+            {
+                "subjectCode": "00.00",
+                "subjectName": "Test subject",
+                "subjectType": "NORMAL",
+                "courseStart": "01/01/2001",
+                "courseEnd": "01/01/2001",
+                "commissionName": "A",
+                "courseCommissionTimes": []
+            },
+            {
+                "subjectCode": "00.00",
+                "subjectName": "Test subject",
+                "subjectType": "NORMAL",
+                "courseStart": "01/01/2001",
+                "courseEnd": "01/01/2001",
+                "commissionName": "B",
+                "courseCommissionTimes": []
+            }
+        ]
+    }
+}
+"#;
+
+const CHOICE: &str = r#"{
+    "subjects": {"00.00": {"name": "Test subject", "credits": 3, "commissions": ["TEST"]}},
+    "week": {
+        "monday": [],
+        "tuesday": [],
+        "wednesday": [{
+            "subject": "00.00",
+            "buildings": ["SDT"],
+            "span": {"start": {"hour": 9, "minutes": 0}, "end": {"hour": 11, "minutes": 30}}
+        }],
+        "thursday": [],
+        "friday": [],
+        "saturday": [],
+        "sunday": []
+    }
+}"#;
+
+fn fixture_api() -> Api {
+    Api::with_transport(
         "http://localhost/api".to_owned(),
-        2022,
-        crate::Semester::Second,
+        FixtureTransport::new(HashMap::from([(
+            "http://localhost/api/commissions/GRADUATE-2022-SecondSemester.json".to_owned(),
+            COMMISSIONS.to_owned(),
+        )])),
     )
-    .await;
+}
 
-    let subject = get_subject_info("00.00".to_owned()).unwrap();
+#[wasm_bindgen_test]
+async fn test_get_from_api() {
+    let commissions = fixture_api()
+        .get_commissions_from_api(2022, crate::Semester::Second)
+        .await
+        .unwrap();
+
+    let subject = commissions
+        .get_subject_info("00.00".to_owned())
+        .unwrap()
+        .unwrap();
 
     assert_eq!(subject.name, "Test subject");
+}
+
+#[wasm_bindgen_test]
+async fn test_missing_period_reports_status() {
+    let error = fixture_api()
+        .get_commissions_from_api(2022, crate::Semester::First)
+        .await
+        .err()
+        .unwrap();
+
+    assert_eq!(error.kind(), ErrorKind::NetworkError);
+    assert_eq!(error.status(), Some(404));
+}
+
+/// Answers each request with the next scripted response, keeping the requests it got.
+#[derive(Clone, Default)]
+struct ScriptedTransport {
+    responses: Rc<RefCell<VecDeque<crate::error::Result<RawResponse>>>>,
+    requests: Rc<RefCell<Vec<RawRequest>>>,
+}
+
+impl ScriptedTransport {
+    fn new(responses: Vec<crate::error::Result<RawResponse>>) -> Self {
+        Self {
+            responses: Rc::new(RefCell::new(responses.into())),
+            ..Default::default()
+        }
+    }
+}
+
+impl Transport for ScriptedTransport {
+    fn fetch<'a>(&'a self, request: &'a RawRequest) -> TransportFuture<'a> {
+        self.requests.borrow_mut().push(request.clone());
+        let response = self.responses.borrow_mut().pop_front().unwrap();
+        Box::pin(async move { response })
+    }
+}
+
+fn response(status: u16, body: &str) -> crate::error::Result<RawResponse> {
+    Ok(RawResponse {
+        status,
+        status_text: String::new(),
+        body: body.to_owned(),
+        etag: Some("\"v1\"".to_owned()),
+        last_modified: None,
+    })
+}
+
+/// An API over `transport` with a cache of its own.
+fn cached_api(transport: &ScriptedTransport) -> Api {
+    let cache_name = format!("test-{}", js_sys::Math::random());
+    Api::with_transport("http://localhost/api".to_owned(), transport.clone()).set_config(
+        ApiConfig::new()
+            .set_cache_name(Some(cache_name))
+            .set_conditional_requests(true),
+    )
+}
+
+async fn get_commissions(api: &Api) -> crate::error::Result<Commissions> {
+    api.get_commissions_from_api(2022, crate::Semester::Second)
+        .await
+}
+
+fn stale_error(api: &Api) -> Option<SchedulerError> {
+    api.commissions_stale_error(2022, crate::Semester::Second, crate::Level::Graduate)
+}
+
+#[wasm_bindgen_test]
+async fn test_not_modified_serves_the_cached_copy() {
+    let transport = ScriptedTransport::new(vec![response(200, COMMISSIONS), response(304, "")]);
+    let api = cached_api(&transport);
+    let age = || api.commissions_cache_age(2022, crate::Semester::Second, crate::Level::Graduate);
+
+    assert_eq!(age().await.unwrap(), None);
+    get_commissions(&api).await.unwrap();
+    let commissions = get_commissions(&api).await.unwrap();
+
+    assert!(commissions
+        .get_subject_info("00.00".to_owned())
+        .unwrap()
+        .is_some());
+    let validators = transport
+        .requests
+        .borrow()
+        .iter()
+        .map(|request| request.if_none_match.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(validators, [None, Some("\"v1\"".to_owned())]);
+    assert!(stale_error(&api).is_none());
+    assert!(matches!(age().await.unwrap(), Some(age) if age >= 0.));
+}
+
+#[wasm_bindgen_test]
+async fn test_server_errors_serve_the_stale_copy() {
+    let transport = ScriptedTransport::new(vec![response(200, COMMISSIONS), response(503, "")]);
+    let api = cached_api(&transport);
+
+    get_commissions(&api).await.unwrap();
+    get_commissions(&api).await.unwrap();
+
+    assert_eq!(stale_error(&api).unwrap().status(), Some(503));
+}
+
+#[wasm_bindgen_test]
+async fn test_network_errors_serve_the_stale_copy() {
+    let transport = ScriptedTransport::new(vec![
+        response(200, COMMISSIONS),
+        Err(SchedulerError::network_error("offline")),
+    ]);
+    let api = cached_api(&transport);
+
+    get_commissions(&api).await.unwrap();
+    get_commissions(&api).await.unwrap();
+
+    assert_eq!(stale_error(&api).unwrap().kind(), ErrorKind::NetworkError);
+}
+
+#[wasm_bindgen_test]
+async fn test_unparsable_pages_are_not_cached() {
+    let transport = ScriptedTransport::new(vec![
+        response(200, COMMISSIONS),
+        response(200, "<html>Log in to the campus network</html>"),
+        Err(SchedulerError::network_error("offline")),
+    ]);
+    let api = cached_api(&transport);
+
+    get_commissions(&api).await.unwrap();
+    get_commissions(&api).await.unwrap();
+    assert!(stale_error(&api).is_some());
+
+    let commissions = get_commissions(&api).await.unwrap();
+    assert!(commissions
+        .get_subject_info("00.00".to_owned())
+        .unwrap()
+        .is_some());
+}
+
+#[wasm_bindgen_test]
+fn test_malformed_json_reports_position() {
+    let error = Commissions::from_json("{\n  \"courseCommissions\": [").unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::MalformedInput);
+    assert_eq!(error.line(), Some(2));
+}
+
+#[wasm_bindgen_test]
+fn test_share_string_round_trip() {
+    let commissions = Commissions::from_json(COMMISSIONS).unwrap();
+    let builder = commissions
+        .create_generator_builder()
+        .set_mandatory_codes(StringArray::from(vec!["00.00".to_owned()]))
+        .unwrap()
+        .pin_commission("00.00".to_owned(), "TEST".to_owned())
+        .unwrap()
+        .set_max_credit_count(Some(20));
+
+    let share = builder.to_share_string().unwrap();
+    let restored = GeneratorBuilder::from_share_string(&commissions, &share).unwrap();
+
+    assert_eq!(restored.config(), builder.config());
+}
+
+fn test_builder() -> GeneratorBuilder {
+    Commissions::from_json(COMMISSIONS)
+        .unwrap()
+        .create_generator_builder()
+        .set_mandatory_codes(StringArray::from(vec!["00.00".to_owned()]))
+        .unwrap()
+}
+
+#[wasm_bindgen_test]
+fn test_diagnosis_of_feasible_builder() {
+    let diagnosis = test_builder().diagnosis();
+
+    assert!(diagnosis.feasible);
+    assert_eq!(diagnosis.examined, 1);
+    assert!(!diagnosis.capped);
+    assert!(diagnosis.eliminated_by.is_empty());
+    assert_eq!(diagnosis.last_eliminated_by, None);
+    assert!(diagnosis.suggestions.is_empty());
+}
+
+#[wasm_bindgen_test]
+fn test_diagnosis_applies_constraints_cumulatively() {
+    // The subject count already rejects the only candidate, so the credit range never sees it.
+    let diagnosis = test_builder()
+        .set_min_subject_count(Some(2))
+        .set_max_credit_count(Some(0))
+        .diagnosis();
+
+    assert!(!diagnosis.feasible);
+    assert_eq!(diagnosis.examined, 1);
+    assert_eq!(diagnosis.eliminated_by, vec![Constraint::SubjectCount]);
+    assert_eq!(diagnosis.last_eliminated_by, Some(Constraint::SubjectCount));
+    assert!(diagnosis
+        .suggestions
+        .contains(&"Widen the subject count range".to_owned()));
+}
+
+#[wasm_bindgen_test]
+fn test_cancelled_diagnosis_is_capped() {
+    let token = CancellationToken::new();
+    token.cancel();
+    let diagnosis = test_builder().set_cancellation_token(&token).diagnosis();
+
+    assert!(diagnosis.capped);
+    assert_eq!(diagnosis.examined, 0);
+}
+
+/// A class of a commission: day, start, end and building.
+type Class<'a> = (&'a str, &'a str, &'a str, &'a str);
+
+/// Commissions given as subject code, commission name and classes.
+fn timed_commissions(commissions: &[(&str, &str, &[Class])]) -> Commissions {
+    let commissions: Vec<_> = commissions
+        .iter()
+        .map(|(code, name, classes)| {
+            let times: Vec<_> = classes
+                .iter()
+                .map(|(day, from, to, building)| {
+                    serde_json::json!({
+                        "day": day,
+                        "hourFrom": from,
+                        "hourTo": to,
+                        "building": building,
+                    })
+                })
+                .collect();
+            serde_json::json!({
+                "subjectCode": code,
+                "subjectName": format!("Subject {code}"),
+                "subjectType": "NORMAL",
+                "courseStart": "01/01/2001",
+                "courseEnd": "01/01/2001",
+                "commissionName": name,
+                "courseCommissionTimes": times,
+            })
+        })
+        .collect();
+    let document = serde_json::json!({"courseCommissions": {"courseCommission": commissions}});
+    Commissions::from_json(&document.to_string()).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn test_diagnosis_finds_conflicts_beyond_pairs() {
+    // Any two of 20.01, 20.02 and 20.03 share the two slots, but not all three. 20.04 fits.
+    const MORNING: &[Class] = &[("MONDAY", "08:00", "10:00", "SDF")];
+    const AFTERNOON: &[Class] = &[("MONDAY", "14:00", "16:00", "SDF")];
+    let commissions = timed_commissions(&[
+        ("20.01", "A", MORNING),
+        ("20.01", "B", AFTERNOON),
+        ("20.02", "A", MORNING),
+        ("20.02", "B", AFTERNOON),
+        ("20.03", "A", MORNING),
+        ("20.03", "B", AFTERNOON),
+        ("20.04", "A", &[("FRIDAY", "08:00", "10:00", "SDF")]),
+    ]);
+    let diagnosis = commissions
+        .create_generator_builder()
+        .set_mandatory_codes(codes(&["20.01", "20.02", "20.03", "20.04"]))
+        .unwrap()
+        .diagnosis();
+
+    assert!(!diagnosis.feasible);
+    assert!(diagnosis.clashes.is_empty());
+    assert_eq!(
+        diagnosis
+            .conflicting_subjects
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        ["20.01", "20.02", "20.03"]
+    );
+    assert!(diagnosis
+        .suggestions
+        .contains(&"Drop one of subjects 20.01, 20.02, 20.03".to_owned()));
+}
+
+/// `10.01` meets on Monday morning at SDF (`A`) or on Wednesday afternoon at SDT (`B`), and
+/// `10.02` right after `10.01 A`, at SDT.
+fn timed_builder() -> GeneratorBuilder {
+    timed_commissions(&[
+        ("10.01", "A", &[("MONDAY", "08:00", "10:00", "SDF")]),
+        ("10.01", "B", &[("WEDNESDAY", "14:00", "16:00", "SDT")]),
+        ("10.02", "A", &[("MONDAY", "10:30", "12:00", "SDT")]),
+    ])
+    .create_generator_builder()
+    .set_mandatory_codes(codes(&["10.01", "10.02"]))
+    .unwrap()
+}
+
+fn choices(mut generator: ChoiceGenerator) -> Vec<OptionInfo> {
+    std::iter::from_fn(|| {
+        let choice = JsValue::from(generator.next_choice().unwrap());
+        (!choice.is_null()).then(|| OptionInfo::from_js(choice).unwrap())
+    })
+    .collect()
+}
+
+fn scores(generator: ChoiceGenerator) -> Vec<Score> {
+    choices(generator)
+        .into_iter()
+        .map(|choice| choice.score.unwrap())
+        .collect()
+}
+
+#[wasm_bindgen_test]
+fn test_score_breakdown() {
+    let weights = ScoringWeights {
+        active_days: 100.,
+        ..Default::default()
+    };
+    let ranked = scores(timed_builder().set_scoring(&weights).ranked_generator(2));
+
+    // Both on Monday: 30 idle minutes and a building change.
+    let monday = &ranked[0];
+    assert_eq!(monday.idle_gap_minutes, 30);
+    assert_eq!(monday.active_days, 1);
+    assert_eq!(monday.earliest_start, 8 * 60);
+    assert_eq!(monday.latest_end, 12 * 60);
+    assert_eq!(monday.building_changes, 1);
+    assert_eq!(monday.blocked_minutes, 0);
+    assert_eq!(monday.total, 30. + 100. + 30.);
+
+    let split = &ranked[1];
+    assert_eq!(split.idle_gap_minutes, 0);
+    assert_eq!(split.active_days, 2);
+    assert_eq!(split.earliest_start, 10 * 60 + 30);
+    assert_eq!(split.latest_end, 16 * 60);
+    assert_eq!(split.building_changes, 0);
+    assert_eq!(split.total, 200.);
+}
+
+#[wasm_bindgen_test]
+fn test_ranking_keeps_the_top_k_best_first() {
+    let weights = ScoringWeights {
+        active_days: 100.,
+        ..Default::default()
+    };
+
+    let ranked = scores(timed_builder().set_scoring(&weights).ranked_generator(1));
+
+    assert_eq!(ranked.len(), 1);
+    assert_eq!(ranked[0].active_days, 1);
+}
+
+#[wasm_bindgen_test]
+fn test_ranking_ties_keep_the_enumeration_order() {
+    // With the default weights both choices score 120.
+    let weights = ScoringWeights::default();
+    let enumerated = scores(timed_builder().set_scoring(&weights).generator());
+    let ranked = scores(timed_builder().set_scoring(&weights).ranked_generator(2));
+
+    assert!(enumerated.iter().all(|score| score.total == 120.));
+    assert_eq!(ranked, enumerated);
+}
+
+fn blocked_slots(slots: &[(Weekday, u8, u8)]) -> BlockedSlots {
+    let slots: Vec<_> = slots
+        .iter()
+        .map(|&(day, start, end)| BlockedSlot {
+            day,
+            start: Time {
+                hour: start,
+                minutes: 0,
+            },
+            end: Time {
+                hour: end,
+                minutes: 0,
+            },
+        })
+        .collect();
+    serde_wasm_bindgen::to_value(&slots).unwrap().into()
+}
+
+#[wasm_bindgen_test]
+fn test_hard_blocked_slots_remove_choices() {
+    let builder = timed_builder()
+        .set_blocked_slots(blocked_slots(&[(Weekday::Monday, 8, 9)]), true)
+        .unwrap();
+
+    let scores = scores(builder.set_scoring(&ScoringWeights::default()).generator());
+
+    assert_eq!(scores.len(), 1);
+    assert_eq!(scores[0].active_days, 2);
+}
+
+#[wasm_bindgen_test]
+fn test_soft_blocked_slots_only_lower_the_score() {
+    let builder = timed_builder()
+        .set_blocked_slots(blocked_slots(&[(Weekday::Monday, 8, 9)]), false)
+        .unwrap();
+
+    let ranked = scores(builder.ranked_generator(2));
+
+    assert_eq!(ranked.len(), 2);
+    assert_eq!(ranked[0].blocked_minutes, 0);
+    assert_eq!(ranked[1].blocked_minutes, 60);
+    assert_eq!(ranked[1].total, 120. + 10. * 60.);
+}
+
+#[wasm_bindgen_test]
+fn test_invalid_blocked_slots_are_rejected() {
+    for slot in [
+        (Weekday::Monday, 10, 10),
+        (Weekday::Monday, 12, 9),
+        (Weekday::Monday, 22, 24),
+    ] {
+        let error = timed_builder()
+            .set_blocked_slots(blocked_slots(&[slot]), true)
+            .err()
+            .unwrap();
+
+        assert_eq!(error.kind(), ErrorKind::MalformedInput);
+    }
+}
+
+#[wasm_bindgen_test]
+fn test_required_free_days() {
+    let days = serde_wasm_bindgen::to_value(&[Weekday::Wednesday]).unwrap();
+    let builder = timed_builder().set_required_free_days(days.into()).unwrap();
+
+    let scores = scores(builder.set_scoring(&ScoringWeights::default()).generator());
+
+    // Only the choice that keeps every class on Monday is left.
+    assert_eq!(scores.len(), 1);
+    assert_eq!(scores[0].earliest_start, 8 * 60);
+}
+
+#[wasm_bindgen_test]
+fn test_max_active_days() {
+    let limited =
+        |max_active_days| active_days(timed_builder().set_max_active_days(max_active_days));
+
+    assert_eq!(limited(Some(1)), [1]);
+    assert_eq!(limited(Some(2)).len(), 2);
+}
+
+/// Days each choice of `builder` has classes on.
+fn active_days(builder: GeneratorBuilder) -> Vec<u32> {
+    scores(builder.set_scoring(&ScoringWeights::default()).generator())
+        .iter()
+        .map(|score| score.active_days)
+        .collect()
+}
+
+fn pin(builder: GeneratorBuilder, name: &str) -> GeneratorBuilder {
+    builder
+        .pin_commission("10.01".to_owned(), name.to_owned())
+        .unwrap()
+}
+
+fn exclude(builder: GeneratorBuilder, name: &str) -> GeneratorBuilder {
+    builder
+        .exclude_commission("10.01".to_owned(), name.to_owned())
+        .unwrap()
+}
+
+#[wasm_bindgen_test]
+fn test_pinned_commissions_are_forced() {
+    // `10.01 A` keeps both subjects on Monday.
+    assert_eq!(active_days(pin(timed_builder(), "A")), [1]);
+    assert_eq!(active_days(pin(timed_builder(), "B")), [2]);
+}
+
+#[wasm_bindgen_test]
+fn test_excluded_commissions_are_removed() {
+    assert_eq!(active_days(exclude(timed_builder(), "A")), [2]);
+}
+
+#[wasm_bindgen_test]
+fn test_the_last_of_pin_and_exclude_wins() {
+    assert_eq!(active_days(exclude(pin(timed_builder(), "A"), "A")), [2]);
+    assert_eq!(active_days(pin(exclude(timed_builder(), "A"), "A")), [1]);
+}
+
+#[wasm_bindgen_test]
+fn test_unknown_commissions_cannot_be_pinned_or_excluded() {
+    let pinned = timed_builder().pin_commission("10.01".to_owned(), "Z".to_owned());
+    let excluded = timed_builder().exclude_commission("10.01".to_owned(), "Z".to_owned());
+
+    assert_eq!(pinned.err().unwrap().kind(), ErrorKind::UnknownCommission);
+    assert_eq!(excluded.err().unwrap().kind(), ErrorKind::UnknownCommission);
+}
+
+#[wasm_bindgen(inline_js = r#"
+export function collect(iterable) {
+    const items = [];
+    for (const item of iterable) {
+        items.push(item);
+    }
+    return items;
+}
+
+export async function collect_async(iterable) {
+    const items = [];
+    for await (const item of iterable) {
+        items.push(item);
+    }
+    return items;
+}
+"#)]
+extern "C" {
+    fn collect(iterable: &JsValue) -> Array;
+    fn collect_async(iterable: &JsValue) -> js_sys::Promise;
+}
+
+#[wasm_bindgen_test]
+fn test_generator_supports_for_of() {
+    let choices = collect(&test_builder().build());
+
+    assert_eq!(choices.length(), 1);
+    assert!(Reflect::has(&choices.get(0), &"week".into()).unwrap());
+}
+
+#[wasm_bindgen_test]
+async fn test_generator_supports_for_await_of() {
+    let choices: Array = JsFuture::from(collect_async(&test_builder().build()))
+        .await
+        .unwrap()
+        .into();
+
+    assert_eq!(choices.length(), 1);
+}
+
+/// Three mandatory subjects with two commissions each, on six different days: eight choices.
+fn eight_choices() -> GeneratorBuilder {
+    timed_commissions(&[
+        ("40.01", "A", &[("MONDAY", "08:00", "10:00", "SDF")]),
+        ("40.01", "B", &[("TUESDAY", "08:00", "10:00", "SDF")]),
+        ("40.02", "A", &[("WEDNESDAY", "08:00", "10:00", "SDF")]),
+        ("40.02", "B", &[("THURSDAY", "08:00", "10:00", "SDF")]),
+        ("40.03", "A", &[("FRIDAY", "08:00", "10:00", "SDF")]),
+        ("40.03", "B", &[("SATURDAY", "08:00", "10:00", "SDF")]),
+    ])
+    .create_generator_builder()
+    .set_mandatory_codes(codes(&["40.01", "40.02", "40.03"]))
+    .unwrap()
+}
+
+#[wasm_bindgen_test]
+fn test_next_choices_comes_in_batches_until_exhausted() {
+    let mut generator = eight_choices().generator();
+    let mut batch = || Array::from(&generator.next_choices(3).unwrap()).length();
+
+    assert_eq!([batch(), batch(), batch(), batch()], [3, 3, 2, 0]);
+    assert_eq!(generator.status(), GeneratorStatus::Exhausted);
+    assert_eq!(generator.yielded(), 8);
+}
+
+#[wasm_bindgen_test]
+fn test_skip_past_the_end() {
+    let mut generator = eight_choices().generator();
+
+    assert_eq!(generator.skip(5), 5);
+    assert_eq!(generator.skip(10), 3);
+    assert!(JsValue::from(generator.next_choice().unwrap()).is_null());
+    assert_eq!(generator.status(), GeneratorStatus::Exhausted);
+}
+
+#[wasm_bindgen_test]
+async fn test_iterator_protocols_yield_every_choice() {
+    let generator = eight_choices().build();
+    assert!(Reflect::has(&generator, &js_sys::Symbol::iterator()).unwrap());
+    assert!(Reflect::has(&generator, &js_sys::Symbol::async_iterator()).unwrap());
+
+    assert_eq!(collect(&generator).length(), 8);
+    assert_eq!(collect(&generator).length(), 0);
+
+    let choices: Array = JsFuture::from(collect_async(&eight_choices().build()))
+        .await
+        .unwrap()
+        .into();
+    assert_eq!(choices.length(), 8);
+}
+
+#[wasm_bindgen_test]
+fn test_partitions_split_every_choice_once() {
+    let commissions = Commissions::from_json(TWO_COMMISSIONS).unwrap();
+    let builder = commissions
+        .create_generator_builder()
+        .set_optional_codes(StringArray::from(vec!["00.00".to_owned()]))
+        .unwrap();
+    let total = builder.count(None).count;
+
+    let parts = builder.partitions(3);
+    let split: u32 = parts
+        .iter()
+        .map(|part| {
+            GeneratorBuilder::with_config(commissions.clone(), part)
+                .unwrap()
+                .count(None)
+                .count
+        })
+        .sum();
+
+    assert!(parts.len() > 1);
+    assert_eq!(split, total);
+}
+
+#[wasm_bindgen_test]
+fn test_partitions_never_exceed_the_pool() {
+    let commissions = Commissions::from_json(TWO_COMMISSIONS).unwrap();
+    let optional = commissions
+        .create_generator_builder()
+        .set_optional_codes(codes(&["00.00"]))
+        .unwrap();
+    let mandatory = commissions
+        .create_generator_builder()
+        .set_mandatory_codes(codes(&["00.00"]))
+        .unwrap();
+
+    for builder in [optional, mandatory] {
+        assert_eq!(builder.partitions(0).len(), 1);
+        for parts in 1..=4 {
+            assert!(builder.partitions(parts).len() <= parts as usize);
+        }
+    }
+}
+
+#[wasm_bindgen_test]
+fn test_worker_pool_needs_commissions_from_json() {
+    let builder = Commissions::new(vec![]).create_generator_builder();
+
+    let error = WorkerPool::new("worker.js".to_owned(), 2)
+        .run(&builder, 10)
+        .err()
+        .unwrap();
+
+    assert_eq!(error.kind(), ErrorKind::WorkerError);
+}
+
+fn cancelled_builder() -> GeneratorBuilder {
+    let token = CancellationToken::new();
+    token.cancel();
+    test_builder().set_cancellation_token(&token)
+}
+
+#[wasm_bindgen_test]
+fn test_cancelled_generator_stops() {
+    let mut generator = cancelled_builder().generator();
+
+    assert!(generator.next_choice().unwrap().is_null());
+    assert_eq!(generator.status(), GeneratorStatus::Cancelled);
+    assert_eq!(generator.yielded(), 0);
+}
+
+#[wasm_bindgen_test]
+fn test_expired_time_budget_stops_generator() {
+    let mut generator = test_builder().set_time_budget_ms(Some(0)).generator();
+
+    assert!(generator.next_choice().unwrap().is_null());
+    assert_eq!(generator.status(), GeneratorStatus::TimedOut);
+}
+
+#[wasm_bindgen_test]
+fn test_limited_count_keeps_the_next_choice() {
+    let mut generator = Commissions::from_json(TWO_COMMISSIONS)
+        .unwrap()
+        .create_generator_builder()
+        .set_mandatory_codes(codes(&["00.00"]))
+        .unwrap()
+        .generator();
+
+    let count = generator.count(Some(1));
+    assert_eq!(count.count, 1);
+    assert!(count.capped);
+
+    assert!(!generator.next_choice().unwrap().is_null());
+    assert!(generator.next_choice().unwrap().is_null());
+    assert_eq!(generator.yielded(), 2);
+    assert_eq!(generator.status(), GeneratorStatus::Exhausted);
+}
+
+#[wasm_bindgen_test]
+fn test_count_honors_cancellation() {
+    let count = cancelled_builder().count(None);
+
+    assert_eq!(count.count, 0);
+    assert!(count.capped);
+    assert!(!test_builder().count(None).capped);
+}
+
+#[wasm_bindgen_test]
+fn test_cut_short_ranking_keeps_its_status() {
+    let mut generator = cancelled_builder().ranked_generator(5);
+
+    assert_eq!(generator.status(), GeneratorStatus::Cancelled);
+    assert!(generator.next_choice().unwrap().is_null());
+    assert_eq!(generator.status(), GeneratorStatus::Cancelled);
+}
+
+#[wasm_bindgen_test]
+fn test_choice_to_ics() {
+    let choice = js_sys::JSON::parse(CHOICE).unwrap();
+
+    let ics = choice_to_ics(
+        choice.into(),
+        "2024-03-04",
+        "2024-07-01",
+        "America/Argentina/Buenos_Aires",
+    )
+    .unwrap();
+
+    assert!(ics.contains("X-WR-TIMEZONE:America/Argentina/Buenos_Aires\r\n"));
+    assert!(ics.contains(
+        "BEGIN:VTIMEZONE\r\n\
+         TZID:America/Argentina/Buenos_Aires\r\n\
+         BEGIN:STANDARD\r\n\
+         DTSTART:19700101T000000\r\n\
+         TZOFFSETFROM:-0300\r\n\
+         TZOFFSETTO:-0300\r\n\
+         END:STANDARD\r\n\
+         END:VTIMEZONE\r\n"
+    ));
+    assert!(ics.contains("DTSTART;TZID=America/Argentina/Buenos_Aires:20240306T090000\r\n"));
+    assert!(ics.contains("DTEND;TZID=America/Argentina/Buenos_Aires:20240306T113000\r\n"));
+    assert!(ics.contains("RRULE:FREQ=WEEKLY;UNTIL=20240702T025959Z\r\n"));
+    assert!(ics.contains("SUMMARY:Test subject (TEST)\r\n"));
+    assert!(ics.contains("LOCATION:SDT\r\n"));
+}
+
+#[wasm_bindgen_test]
+fn test_choice_to_ics_describes_offset_changes() {
+    let choice = js_sys::JSON::parse(CHOICE).unwrap();
+
+    let ics = choice_to_ics(choice.into(), "2024-03-04", "2024-04-30", "Europe/Madrid").unwrap();
+
+    assert!(ics.contains(
+        "BEGIN:STANDARD\r\n\
+         DTSTART:19700101T000000\r\n\
+         TZOFFSETFROM:+0100\r\n\
+         TZOFFSETTO:+0100\r\n\
+         END:STANDARD\r\n\
+         BEGIN:DAYLIGHT\r\n\
+         DTSTART:20240331T020000\r\n\
+         TZOFFSETFROM:+0100\r\n\
+         TZOFFSETTO:+0200\r\n\
+         END:DAYLIGHT\r\n"
+    ));
+    assert!(ics.contains("RRULE:FREQ=WEEKLY;UNTIL=20240430T215959Z\r\n"));
+}
+
+#[wasm_bindgen_test]
+fn test_choice_to_ics_rejects_invalid_timezones() {
+    for timezone in [
+        "",
+        "UTC\r\nX-INJECTED:1",
+        "Europe/Paris;VALUE=DATE",
+        "Mars/Olympus_Mons",
+    ] {
+        let error = choice_to_ics(
+            js_sys::JSON::parse(CHOICE).unwrap().into(),
+            "2024-03-04",
+            "2024-07-01",
+            timezone,
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::MalformedInput);
+    }
+}
+
+#[wasm_bindgen_test]
+fn test_choice_to_csv() {
+    let csv = choice_to_csv(js_sys::JSON::parse(CHOICE).unwrap().into()).unwrap();
+
+    assert_eq!(
+        csv,
+        "day,subject,name,commissions,start,end,buildings,classroom\r\n\
+         Wednesday,00.00,Test subject,TEST,09:00,11:30,SDT,\r\n"
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_choice_to_svg_fits_classes() {
+    let svg = choice_to_svg(js_sys::JSON::parse(CHOICE).unwrap().into(), None).unwrap();
+
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains(">09:00</text>"));
+    assert!(!svg.contains(">08:00</text>"));
+    assert!(svg.contains(">Wednesday</text>"));
+    assert!(!svg.contains(">Saturday</text>"));
+}
+
+#[wasm_bindgen_test]
+fn test_choice_to_svg_colors_tasks_without_subject() {
+    let choice = js_sys::JSON::parse(CHOICE).unwrap();
+    Reflect::set(&choice, &"subjects".into(), &js_sys::Object::new()).unwrap();
+
+    let svg = choice_to_svg(choice.into(), None).unwrap();
+
+    assert!(svg.contains(">00.00</text>"));
+}
+
+/// `00.00` unlocks `01.02` and `01.03`, which together unlock `01.04`. `00.00` is listed twice
+/// and `01.04` lists `01.02` twice.
+const PLAN: &str = r#"
+{
+    "sections": [
+        {
+            "terms": [
+                {
+                    "term": {"year": 1, "period": 1},
+                    "entries": [
+                        {"Subject": {"code": "00.00", "name": "Test subject", "credits": 3, "dependencies": []}}
+                    ]
+                },
+                {
+                    "term": {"year": 1, "period": 2},
+                    "entries": [
+                        {"Subject": {"code": "01.02", "name": "Left", "credits": 3, "dependencies": ["00.00"]}},
+                        {"Subject": {"code": "01.03", "name": "Right", "credits": 3, "dependencies": ["00.00"]}}
+                    ]
+                }
+            ],
+            "without_term": []
+        },
+        {
+            "terms": [],
+            "without_term": [
+                {"Subject": {"code": "00.00", "name": "Test subject", "credits": 3, "dependencies": []}},
+                {"Subject": {"code": "01.04", "name": "Seminar \"A\"", "credits": 6, "dependencies": ["01.02", "01.03", "01.02"]}}
+            ]
+        }
+    ]
+}
+"#;
+
+fn codes(codes: &[&str]) -> StringArray {
+    codes
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .into()
+}
+
+fn strings(array: StringArray) -> Vec<String> {
+    array.try_into().unwrap()
+}
+
+#[wasm_bindgen_test]
+fn test_eligible_subjects_lists_each_subject_once() {
+    let plan = SubjectPlan::from_json(PLAN).unwrap();
+
+    assert_eq!(
+        strings(plan.eligible_subjects(codes(&[])).unwrap()),
+        ["00.00"]
+    );
+    assert_eq!(
+        strings(plan.eligible_subjects(codes(&["00.00"])).unwrap()),
+        ["01.02", "01.03"]
+    );
+    assert_eq!(
+        strings(plan.eligible_subjects(codes(&["00.00", "01.02"])).unwrap()),
+        ["01.03"]
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_eligible_offered_subjects() {
+    let plan = SubjectPlan::from_json(PLAN).unwrap();
+    let commissions = Commissions::from_json(COMMISSIONS).unwrap();
+
+    let offered = |completed: &[&str]| {
+        strings(
+            plan.eligible_offered_subjects(codes(completed), &commissions)
+                .unwrap(),
+        )
+    };
+    assert_eq!(offered(&[]), ["00.00"]);
+    assert!(offered(&["00.00"]).is_empty());
+}
+
+/// `02.01` and `02.02` depend on each other, and `02.01` lists its dependency twice.
+const CYCLIC_PLAN: &str = r#"
+{
+    "sections": [
+        {
+            "terms": [],
+            "without_term": [
+                {"Subject": {"code": "02.01", "name": "First", "credits": 3, "dependencies": ["02.02", "02.02"]}},
+                {"Subject": {"code": "02.02", "name": "Second", "credits": 3, "dependencies": ["02.01"]}}
+            ]
+        }
+    ]
+}
+"#;
+
+#[wasm_bindgen_test]
+fn test_dependencies_of_a_diamond() {
+    let plan = SubjectPlan::from_json(PLAN).unwrap();
+    let list = |array: Result<Option<StringArray>, _>| strings(array.unwrap().unwrap());
+
+    assert_eq!(
+        list(plan.all_prerequisites("01.04".to_owned())),
+        ["01.02", "01.03", "00.00"]
+    );
+    assert_eq!(
+        list(plan.dependents("00.00".to_owned())),
+        ["01.02", "01.03"]
+    );
+    assert_eq!(list(plan.dependents("01.02".to_owned())), ["01.04"]);
+    assert_eq!(plan.unlock_count("00.00".to_owned()).unwrap(), Some(3));
+    assert_eq!(plan.unlock_count("01.04".to_owned()).unwrap(), Some(0));
+    assert!(plan.dependents("09.09".to_owned()).unwrap().is_none());
+}
+
+#[wasm_bindgen_test]
+fn test_dependencies_of_a_cycle() {
+    let plan = SubjectPlan::from_json(CYCLIC_PLAN).unwrap();
+    let list = |array: Result<Option<StringArray>, _>| strings(array.unwrap().unwrap());
+
+    assert_eq!(list(plan.all_prerequisites("02.01".to_owned())), ["02.02"]);
+    assert_eq!(list(plan.dependents("02.02".to_owned())), ["02.01"]);
+    assert_eq!(list(plan.dependents("02.01".to_owned())), ["02.02"]);
+    assert_eq!(plan.unlock_count("02.01".to_owned()).unwrap(), Some(1));
+}
+
+#[wasm_bindgen_test]
+fn test_plan_to_dot() {
+    let plan = SubjectPlan::from_json(PLAN).unwrap();
+    let dot = plan.to_dot(Some(codes(&["00.00", "99.99"]))).unwrap();
+
+    assert_eq!(
+        dot,
+        r##"digraph plan {
+    rankdir=LR;
+    node [shape=box];
+    subgraph cluster_0 {
+        label="Section 1";
+        subgraph cluster_0_0 {
+            label="Year 1, period 1";
+            "00.00" [label="00.00\nTest subject", style=filled, fillcolor="#c8e6c9"];
+        }
+        subgraph cluster_0_1 {
+            label="Year 1, period 2";
+            "01.02" [label="01.02\nLeft", style=filled, fillcolor="#fff9c4"];
+            "01.03" [label="01.03\nRight", style=filled, fillcolor="#fff9c4"];
+        }
+    }
+    subgraph cluster_1 {
+        label="Section 2";
+        "01.04" [label="01.04\nSeminar \"A\""];
+    }
+    "00.00" -> "01.02";
+    "00.00" -> "01.03";
+    "01.02" -> "01.04";
+    "01.03" -> "01.04";
+}
+"##
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_plan_to_mermaid() {
+    let plan = SubjectPlan::from_json(PLAN).unwrap();
+    let mermaid = plan.to_mermaid(Some(codes(&["00.00", "99.99"]))).unwrap();
+
+    assert_eq!(
+        mermaid,
+        r##"flowchart LR
+    subgraph section_0["Section 1"]
+        subgraph term_0_0["Year 1, period 1"]
+            s00_00["00.00<br/>Test subject"]
+        end
+        subgraph term_0_1["Year 1, period 2"]
+            s01_02["01.02<br/>Left"]
+            s01_03["01.03<br/>Right"]
+        end
+    end
+    subgraph section_1["Section 2"]
+        s01_04["01.04<br/>Seminar #quot;A#quot;"]
+    end
+    s00_00 --> s01_02
+    s00_00 --> s01_03
+    s01_02 --> s01_04
+    s01_03 --> s01_04
+    classDef completed fill:#c8e6c9
+    classDef eligible fill:#fff9c4
+    class s00_00 completed
+    class s01_02 eligible
+    class s01_03 eligible
+"##
+    );
+}
+
+/// Declarations derived from the serde types, as they end up in the generated `.d.ts`.
+const DECLARATIONS: &[&str] = &[
+    OptionInfo::DECL,
+    Subject::DECL,
+    CommissionInfo::DECL,
+    Task::DECL,
+    Span::DECL,
+    Time::DECL,
+    Score::DECL,
+    ScoringWeights::DECL,
+    Weekday::DECL,
+    BlockedSlot::DECL,
+    GeneratorConfig::DECL,
+    DiagnosisInfo::DECL,
+    Constraint::DECL,
+    Clash::DECL,
+    PrunedSubject::DECL,
+    SvgConfig::DECL,
+    PeriodDescriptor::DECL,
+    PeriodSemester::DECL,
+    PeriodLevel::DECL,
+    PlanDescriptor::DECL,
+];
+
+/// Names declared elsewhere: wasm-bindgen enums, `tsify::declare` aliases and TypeScript's own
+/// types.
+const EXTERNAL_TYPES: &[&str] = &[
+    "Semester",
+    "Level",
+    "CommissionRef",
+    "Code",
+    "EnumMap",
+    "Map",
+    "Record",
+];
+
+#[wasm_bindgen_test]
+fn test_typescript_declarations_only_reference_declared_types() {
+    let declared: HashSet<_> = DECLARATIONS
+        .iter()
+        .filter_map(|decl| decl.split_whitespace().nth(2))
+        .chain(EXTERNAL_TYPES.iter().copied())
+        .collect();
+
+    for decl in DECLARATIONS {
+        let referenced = decl
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|word| word.starts_with(|c: char| c.is_ascii_uppercase()));
+        for name in referenced {
+            assert!(declared.contains(name), "{name} is not declared:\n{decl}");
+        }
+    }
+}
+
+#[wasm_bindgen_test]
+fn test_choice_serialization_matches_declaration() {
+    let info = OptionInfo::from_js(js_sys::JSON::parse(CHOICE).unwrap()).unwrap();
+    let choice = serde_wasm_bindgen::to_value(&info).unwrap();
 
-    //api_mock.assert();
+    // `EnumMap` is declared as a `Map` too.
+    for field in ["subjects", "week"] {
+        let value = Reflect::get(&choice, &field.into()).unwrap();
+        let declared_as_map = ["Map", "EnumMap"]
+            .iter()
+            .any(|map| OptionInfo::DECL.contains(&format!("{field}: {map}<")));
+        assert_eq!(
+            value.is_instance_of::<js_sys::Map>(),
+            declared_as_map,
+            "{field} doesn't serialize as declared"
+        );
+    }
 }
@@ -0,0 +1,335 @@
+use std::{collections::HashMap, future::Future, pin::Pin};
+
+use js_sys::{Function, Promise};
+use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Headers, Request, RequestInit, RequestMode, Response, Window, WorkerGlobalScope};
+
+use crate::error::{js_error_message, Result, SchedulerError};
+
+/// A GET request, optionally conditional on the validators of a previously seen response.
+#[derive(Debug, Clone, Default)]
+pub struct RawRequest {
+    pub url: String,
+    pub if_none_match: Option<String>,
+    pub if_modified_since: Option<String>,
+}
+
+impl RawRequest {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn headers(&self) -> Result<Headers> {
+        let headers = Headers::new().map_err(network_error(&self.url))?;
+        let validators = [
+            ("If-None-Match", &self.if_none_match),
+            ("If-Modified-Since", &self.if_modified_since),
+        ];
+        for (name, value) in validators {
+            if let Some(value) = value {
+                headers.set(name, value).map_err(network_error(&self.url))?;
+            }
+        }
+        Ok(headers)
+    }
+
+    fn init(&self) -> Result<RequestInit> {
+        let headers = self.headers()?;
+        let mut opts = RequestInit::new();
+        opts.method("GET").mode(RequestMode::Cors).headers(&headers);
+        Ok(opts)
+    }
+}
+
+pub struct RawResponse {
+    pub status: u16,
+    pub status_text: String,
+    pub body: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl RawResponse {
+    fn with_body(status: u16, status_text: &str, body: String) -> Self {
+        Self {
+            status,
+            status_text: status_text.to_owned(),
+            body,
+            etag: None,
+            last_modified: None,
+        }
+    }
+
+    pub fn not_modified(&self) -> bool {
+        self.status == 304
+    }
+
+    pub fn ok(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Returns the body of a successful response, or the HTTP status as an error otherwise.
+    pub fn into_body(self, url: &str) -> Result<String> {
+        if self.ok() {
+            Ok(self.body)
+        } else {
+            Err(SchedulerError::http_status(
+                url,
+                self.status,
+                self.status_text,
+            ))
+        }
+    }
+}
+
+pub type TransportFuture<'a> = Pin<Box<dyn Future<Output = Result<RawResponse>> + 'a>>;
+
+/// Something that can retrieve the documents served under an [`Api`](crate::api::Api) base URL.
+pub trait Transport {
+    fn fetch<'a>(&'a self, request: &'a RawRequest) -> TransportFuture<'a>;
+}
+
+fn network_error(url: &str) -> impl Fn(JsValue) -> SchedulerError + '_ {
+    move |e| SchedulerError::network_error(js_error_message(&e)).with_url(url)
+}
+
+fn new_request(request: &RawRequest) -> Result<Request> {
+    Request::new_with_str_and_init(&request.url, &request.init()?)
+        .map_err(network_error(&request.url))
+}
+
+async fn read_response(url: &str, resp: Response) -> Result<RawResponse> {
+    let body = JsFuture::from(resp.text().map_err(network_error(url))?)
+        .await
+        .map_err(network_error(url))?
+        .as_string()
+        .ok_or_else(|| SchedulerError::network_error("Response body is not text").with_url(url))?;
+
+    let headers = resp.headers();
+    Ok(RawResponse {
+        status: resp.status(),
+        status_text: resp.status_text(),
+        body,
+        etag: headers.get("ETag").ok().flatten(),
+        last_modified: headers.get("Last-Modified").ok().flatten(),
+    })
+}
+
+/// Awaits a `fetch`-like promise, accepting either a `Response` or a plain string body.
+async fn resolve_fetch(url: &str, promise: Promise) -> Result<RawResponse> {
+    let value = JsFuture::from(promise).await.map_err(network_error(url))?;
+
+    if let Some(body) = value.as_string() {
+        return Ok(RawResponse::with_body(200, "OK", body));
+    }
+
+    let resp: Response = value.dyn_into().map_err(|_| {
+        SchedulerError::network_error("fetch must resolve to a Response or a string").with_url(url)
+    })?;
+    read_response(url, resp).await
+}
+
+pub struct WindowTransport(Window);
+
+impl Transport for WindowTransport {
+    fn fetch<'a>(&'a self, request: &'a RawRequest) -> TransportFuture<'a> {
+        Box::pin(async move {
+            let url = &request.url;
+            resolve_fetch(url, self.0.fetch_with_request(&new_request(request)?)).await
+        })
+    }
+}
+
+pub struct WorkerTransport(WorkerGlobalScope);
+
+impl Transport for WorkerTransport {
+    fn fetch<'a>(&'a self, request: &'a RawRequest) -> TransportFuture<'a> {
+        Box::pin(async move {
+            let url = &request.url;
+            resolve_fetch(url, self.0.fetch_with_request(&new_request(request)?)).await
+        })
+    }
+}
+
+/// Delegates to a user supplied `(url: string, init: RequestInit) => Promise<Response | string>`.
+pub struct CallbackTransport(Function);
+
+impl CallbackTransport {
+    pub fn new(callback: Function) -> Self {
+        Self(callback)
+    }
+}
+
+impl Transport for CallbackTransport {
+    fn fetch<'a>(&'a self, request: &'a RawRequest) -> TransportFuture<'a> {
+        Box::pin(async move {
+            let url = &request.url;
+            let init = request.init()?;
+            let result = self
+                .0
+                .call2(&JsValue::undefined(), &url.into(), &init)
+                .map_err(network_error(url))?;
+            resolve_fetch(url, Promise::resolve(&result)).await
+        })
+    }
+}
+
+/// Serves documents from memory, answering 404 for anything it does not know about.
+pub struct FixtureTransport(HashMap<String, String>);
+
+impl FixtureTransport {
+    pub fn new(fixtures: HashMap<String, String>) -> Self {
+        Self(fixtures)
+    }
+}
+
+impl Transport for FixtureTransport {
+    fn fetch<'a>(&'a self, request: &'a RawRequest) -> TransportFuture<'a> {
+        let response = match self.0.get(&request.url) {
+            Some(body) => RawResponse::with_body(200, "OK", body.clone()),
+            None => RawResponse::with_body(404, "Not Found", String::new()),
+        };
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+/// The transports a global scope can back, in order of preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TransportKind {
+    Window,
+    Worker,
+    Fetch,
+}
+
+/// What the global scope the module was loaded in looks like.
+#[derive(Debug, Clone, Copy)]
+struct ScopeFeatures {
+    window: bool,
+    worker: bool,
+    fetch: bool,
+}
+
+impl ScopeFeatures {
+    fn detect(global: &JsValue) -> Self {
+        Self {
+            window: global.is_instance_of::<Window>(),
+            worker: global.is_instance_of::<WorkerGlobalScope>(),
+            fetch: matches!(js_sys::Reflect::get(global, &"fetch".into()), Ok(f) if f.is_function()),
+        }
+    }
+
+    fn transport_kind(self) -> Option<TransportKind> {
+        if self.window {
+            Some(TransportKind::Window)
+        } else if self.worker {
+            Some(TransportKind::Worker)
+        } else if self.fetch {
+            // Runtimes such as Node expose `fetch` without being a window or a worker.
+            Some(TransportKind::Fetch)
+        } else {
+            None
+        }
+    }
+}
+
+/// Picks the transport matching the global scope the module was loaded in.
+pub fn default_transport() -> Result<Box<dyn Transport>> {
+    let global = js_sys::global();
+
+    match ScopeFeatures::detect(&global).transport_kind() {
+        Some(TransportKind::Window) => Ok(Box::new(WindowTransport(global.unchecked_into()))),
+        Some(TransportKind::Worker) => Ok(Box::new(WorkerTransport(global.unchecked_into()))),
+        Some(TransportKind::Fetch) => {
+            let fetch = js_sys::Reflect::get(&global, &"fetch".into())
+                .map_err(|e| SchedulerError::network_error(js_error_message(&e)))?;
+            Ok(Box::new(CallbackTransport::new(
+                fetch.unchecked_into::<Function>().bind(&global),
+            )))
+        }
+        None => Err(SchedulerError::network_error(
+            "No fetch implementation is available in this environment",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Future,
+        pin::pin,
+        task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+    };
+
+    use super::*;
+    use crate::error::ErrorKind;
+
+    /// Polls a future that never waits, such as the ones returned by `FixtureTransport`.
+    fn ready<T>(future: impl Future<Output = T>) -> T {
+        fn raw_waker() -> RawWaker {
+            const VTABLE: RawWakerVTable =
+                RawWakerVTable::new(|_| raw_waker(), |_| {}, |_| {}, |_| {});
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        let waker = unsafe { Waker::from_raw(raw_waker()) };
+        match pin!(future).poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(value) => value,
+            Poll::Pending => panic!("the future is not ready"),
+        }
+    }
+
+    fn fixtures() -> FixtureTransport {
+        FixtureTransport::new(HashMap::from([(
+            "http://localhost/index.json".to_owned(),
+            "{}".to_owned(),
+        )]))
+    }
+
+    #[test]
+    fn fixture_transport_serves_known_urls() {
+        let url = "http://localhost/index.json";
+        let response = ready(fixtures().fetch(&RawRequest::new(url))).unwrap();
+
+        assert!(response.ok());
+        assert!(!response.not_modified());
+        assert_eq!(response.into_body(url).unwrap(), "{}");
+    }
+
+    #[test]
+    fn fixture_transport_answers_404_for_unknown_urls() {
+        let url = "http://localhost/missing.json";
+        let response = ready(fixtures().fetch(&RawRequest::new(url))).unwrap();
+
+        assert_eq!(response.status, 404);
+        let error = response.into_body(url).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NetworkError);
+        assert_eq!(error.status(), Some(404));
+        assert_eq!(error.url().as_deref(), Some(url));
+    }
+
+    #[test]
+    fn default_transport_prefers_window_then_worker_then_fetch() {
+        let scope = |window, worker, fetch| ScopeFeatures {
+            window,
+            worker,
+            fetch,
+        };
+
+        assert_eq!(
+            scope(true, false, true).transport_kind(),
+            Some(TransportKind::Window)
+        );
+        assert_eq!(
+            scope(false, true, true).transport_kind(),
+            Some(TransportKind::Worker)
+        );
+        assert_eq!(
+            scope(false, false, true).transport_kind(),
+            Some(TransportKind::Fetch)
+        );
+        assert_eq!(scope(false, false, false).transport_kind(), None);
+    }
+}
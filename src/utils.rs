@@ -8,3 +8,44 @@ pub fn set_panic_hook() {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
 }
+
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Unpadded base64 with the URL and filename safe alphabet (RFC 4648 §5).
+pub fn base64url_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len() * 4 / 3 + 3);
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (i, &b)| group | (b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            encoded.push(BASE64URL[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    encoded
+}
+
+/// Decodes `base64url_encode` output, also accepting trailing padding.
+pub fn base64url_decode(encoded: &str) -> Option<Vec<u8>> {
+    let digits = encoded
+        .trim_end_matches('=')
+        .bytes()
+        .map(|c| BASE64URL.iter().position(|&d| d == c).map(|d| d as u32))
+        .collect::<Option<Vec<_>>>()?;
+    if digits.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (i, &d)| group | d << (18 - 6 * i));
+        for i in 0..chunk.len() - 1 {
+            bytes.push((group >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(bytes)
+}
@@ -0,0 +1,360 @@
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use js_sys::{Array, Function, Object, Promise, Reflect};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{prelude::*, JsCast};
+use web_sys::{
+    DedicatedWorkerGlobalScope, ErrorEvent, MessageEvent, Worker, WorkerOptions, WorkerType,
+};
+
+use crate::{
+    commissions::Commissions,
+    config::GeneratorConfig,
+    error::{js_error_message, Result, SchedulerError},
+    generator::GeneratorBuilder,
+};
+
+#[wasm_bindgen(typescript_custom_section)]
+const IWORKER: &'static str = r#"
+export type ChoiceMessage =
+    | { kind: "choices", choices: Choice[] }
+    | { kind: "done", count: number }
+    | { kind: "error", message: string };
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "Promise<Choice[] | null>")]
+    pub type ChoiceBatchPromise;
+}
+
+/// What a `WorkerPool` posts to each of its workers.
+#[derive(Serialize, Deserialize)]
+struct GeneratorJob {
+    commissions: String,
+    config: GeneratorConfig,
+    batch_size: u32,
+}
+
+fn message(kind: &str, field: Option<(&str, &JsValue)>) -> JsValue {
+    let message = Object::new();
+    Reflect::set(&message, &"kind".into(), &kind.into()).unwrap();
+    if let Some((name, value)) = field {
+        Reflect::set(&message, &name.into(), value).unwrap();
+    }
+    message.into()
+}
+
+fn post(scope: &DedicatedWorkerGlobalScope, message: &JsValue) -> Result<()> {
+    scope
+        .post_message(message)
+        .map_err(|e| SchedulerError::worker_error(js_error_message(&e)))
+}
+
+fn run_job(scope: &DedicatedWorkerGlobalScope, job: JsValue) -> Result<()> {
+    let job: GeneratorJob = serde_wasm_bindgen::from_value(job)?;
+    let commissions = Commissions::from_json(&job.commissions)?;
+    let mut generator = GeneratorBuilder::with_config(commissions, &job.config)?.generator();
+
+    let mut count = 0;
+    loop {
+        let batch: Array = generator
+            .next_choices(job.batch_size.max(1))?
+            .unchecked_into();
+        if batch.length() == 0 {
+            break;
+        }
+        count += batch.length();
+        post(scope, &message("choices", Some(("choices", &batch))))?;
+    }
+    post(scope, &message("done", Some(("count", &count.into()))))
+}
+
+/// Runs a job posted by a `WorkerPool`, streaming its choices back.
+#[wasm_bindgen]
+pub fn run_generator_job(job: JsValue) -> Result<()> {
+    let scope: DedicatedWorkerGlobalScope = js_sys::global().dyn_into().map_err(|_| {
+        SchedulerError::worker_error("run_generator_job must run inside a dedicated worker")
+    })?;
+    let result = run_job(&scope, job);
+    if let Err(e) = &result {
+        post(
+            &scope,
+            &message("error", Some(("message", &e.to_string().into()))),
+        )?;
+    }
+    result
+}
+
+/// A `ChoiceMessage` posted by a worker.
+enum WorkerMessage {
+    Choices(JsValue),
+    Done,
+    Error(String),
+}
+
+impl WorkerMessage {
+    fn parse(data: &JsValue) -> Option<Self> {
+        let field = |name: &str| Reflect::get(data, &name.into()).unwrap_or_default();
+        match field("kind").as_string().as_deref() {
+            Some("choices") => Some(Self::Choices(field("choices"))),
+            Some("done") => Some(Self::Done),
+            Some("error") => Some(Self::Error(
+                field("message").as_string().unwrap_or_default(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ChannelState {
+    batches: VecDeque<JsValue>,
+    /// Whether each worker, by index, finished or failed. A failing worker may report both an
+    /// `error` message and an `error` event, so finishing is idempotent.
+    finished: Vec<bool>,
+    error: Option<SchedulerError>,
+    waiters: VecDeque<(Function, Function)>,
+}
+
+impl ChannelState {
+    fn new(workers: usize) -> Self {
+        Self {
+            finished: vec![false; workers],
+            ..Default::default()
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.finished.iter().any(|&finished| !finished)
+    }
+
+    /// Settles as many pending `recv` calls as the received messages allow.
+    fn flush(&mut self) {
+        while let Some((resolve, reject)) = self.waiters.front() {
+            let settled = if let Some(batch) = self.batches.pop_front() {
+                resolve.call1(&JsValue::NULL, &batch)
+            } else if let Some(error) = &self.error {
+                reject.call1(&JsValue::NULL, &error.clone().into())
+            } else if !self.is_running() {
+                resolve.call1(&JsValue::NULL, &JsValue::NULL)
+            } else {
+                break;
+            };
+            settled.unwrap();
+            self.waiters.pop_front();
+        }
+    }
+
+    fn receive(&mut self, worker: usize, message: WorkerMessage) {
+        match message {
+            WorkerMessage::Choices(batch) => self.batches.push_back(batch),
+            WorkerMessage::Done => self.finish(worker),
+            WorkerMessage::Error(message) => {
+                return self.fail(worker, SchedulerError::worker_error(message))
+            }
+        }
+        self.flush();
+    }
+
+    fn fail(&mut self, worker: usize, error: SchedulerError) {
+        self.finish(worker);
+        self.error.get_or_insert(error);
+        self.flush();
+    }
+
+    fn finish(&mut self, worker: usize) {
+        if let Some(finished) = self.finished.get_mut(worker) {
+            *finished = true;
+        }
+    }
+
+    fn finish_all(&mut self) {
+        self.finished.fill(true);
+        self.flush();
+    }
+}
+
+/// Choices streamed back by the workers of a `WorkerPool` run, in no particular order.
+#[wasm_bindgen]
+pub struct ChoiceChannel {
+    state: Rc<RefCell<ChannelState>>,
+    workers: Vec<Worker>,
+    _on_message: Vec<Closure<dyn FnMut(MessageEvent)>>,
+    _on_error: Vec<Closure<dyn FnMut(ErrorEvent)>>,
+}
+
+#[wasm_bindgen]
+impl ChoiceChannel {
+    /// Resolves with the next batch of choices, or `null` once every worker is done. Rejects
+    /// with the first error reported by a worker.
+    pub fn recv(&self) -> ChoiceBatchPromise {
+        let state = self.state.clone();
+        let promise = Promise::new(&mut |resolve, reject| {
+            let mut state = state.borrow_mut();
+            state.waiters.push_back((resolve, reject));
+            state.flush();
+        });
+        JsValue::from(promise).into()
+    }
+
+    /// Whether some worker is still enumerating.
+    pub fn is_running(&self) -> bool {
+        self.state.borrow().is_running()
+    }
+
+    /// Stops every worker. Batches already received can still be read.
+    pub fn close(&self) {
+        self.workers.iter().for_each(Worker::terminate);
+        self.state.borrow_mut().finish_all();
+    }
+}
+
+impl Drop for ChoiceChannel {
+    fn drop(&mut self) {
+        self.workers.iter().for_each(Worker::terminate);
+    }
+}
+
+/// Spawns workers running `script_url`, which must pass every message to `run_generator_job`.
+#[wasm_bindgen]
+pub struct WorkerPool {
+    script_url: String,
+    size: u32,
+    module: bool,
+}
+
+#[wasm_bindgen]
+impl WorkerPool {
+    #[wasm_bindgen(constructor)]
+    pub fn new(script_url: String, size: u32) -> WorkerPool {
+        Self {
+            script_url,
+            size: size.max(1),
+            module: false,
+        }
+    }
+
+    /// Spawns module workers instead of classic ones.
+    pub fn set_module(mut self, module: bool) -> WorkerPool {
+        self.module = module;
+        self
+    }
+
+    fn spawn(&self) -> Result<Worker> {
+        let mut options = WorkerOptions::new();
+        if self.module {
+            options.type_(WorkerType::Module);
+        }
+        Worker::new_with_options(&self.script_url, &options)
+            .map_err(|e| SchedulerError::worker_error(js_error_message(&e)))
+    }
+
+    /// Partitions the choices of `builder` between fresh workers and streams them back in
+    /// batches of `batch_size`. Ranking is not supported, every worker yields its choices in
+    /// enumeration order.
+    pub fn run(&self, builder: &GeneratorBuilder, batch_size: u32) -> Result<ChoiceChannel> {
+        let commissions = builder.commissions().source().ok_or_else(|| {
+            SchedulerError::worker_error("Only commissions loaded from JSON can be sent to workers")
+        })?;
+        let jobs = builder.partitions(self.size);
+        let state = Rc::new(RefCell::new(ChannelState::new(jobs.len())));
+        let mut channel = ChoiceChannel {
+            state: state.clone(),
+            workers: vec![],
+            _on_message: vec![],
+            _on_error: vec![],
+        };
+
+        for (index, config) in jobs.into_iter().enumerate() {
+            let worker = self.spawn()?;
+            let on_message = {
+                let state = state.clone();
+                let worker = worker.clone();
+                Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                    let Some(message) = WorkerMessage::parse(&event.data()) else {
+                        return;
+                    };
+                    let finished = !matches!(message, WorkerMessage::Choices(_));
+                    state.borrow_mut().receive(index, message);
+                    if finished {
+                        worker.terminate();
+                    }
+                })
+            };
+            let on_error = {
+                let state = state.clone();
+                Closure::<dyn FnMut(ErrorEvent)>::new(move |event: ErrorEvent| {
+                    state
+                        .borrow_mut()
+                        .fail(index, SchedulerError::worker_error(event.message()));
+                })
+            };
+            worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            worker.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+            let job = GeneratorJob {
+                commissions: commissions.to_owned(),
+                config,
+                batch_size,
+            };
+            let job = serde_wasm_bindgen::to_value(&job)?;
+            worker
+                .post_message(&job)
+                .map_err(|e| SchedulerError::worker_error(js_error_message(&e)))?;
+
+            channel.workers.push(worker);
+            channel._on_message.push(on_message);
+            channel._on_error.push(on_error);
+        }
+        Ok(channel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn channel_runs_until_every_worker_is_done() {
+        let mut state = ChannelState::new(2);
+        state.receive(0, WorkerMessage::Choices(JsValue::NULL));
+        state.receive(0, WorkerMessage::Done);
+        assert!(state.is_running());
+
+        state.receive(1, WorkerMessage::Done);
+        assert!(!state.is_running());
+        assert_eq!(state.batches.len(), 1);
+    }
+
+    #[test]
+    fn failing_worker_only_finishes_itself() {
+        let mut state = ChannelState::new(2);
+        state.receive(0, WorkerMessage::Error("boom".to_owned()));
+        // The same failure may also surface as an `error` event.
+        state.fail(0, SchedulerError::worker_error("boom"));
+
+        assert!(state.is_running());
+        assert_eq!(
+            state.error.as_ref().map(|e| e.kind()),
+            Some(ErrorKind::WorkerError)
+        );
+        assert_eq!(
+            state.error.as_ref().map(|e| e.message()).as_deref(),
+            Some("boom")
+        );
+
+        state.receive(1, WorkerMessage::Choices(JsValue::NULL));
+        assert_eq!(state.batches.len(), 1);
+    }
+
+    #[test]
+    fn closing_finishes_every_worker() {
+        let mut state = ChannelState::new(3);
+        state.receive(1, WorkerMessage::Done);
+        state.finish_all();
+
+        assert!(!state.is_running());
+    }
+}